@@ -1,5 +1,70 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::ops::{Deref, Range};
+
+/// Range of source offsets a node was parsed from, using the same offsets as tokens
+pub type Span = Range<usize>;
+
+/// A node along with the span it was parsed from
+///
+/// Operands, conditions, callees, and statement expressions carry spans. Nodes
+/// built by hand (like in tests or builtins) don't have one, so spans are
+/// ignored when comparing nodes
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Option<Span>,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Spanned {
+            node,
+            span: Some(span),
+        }
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Self {
+        Spanned { node, span: None }
+    }
+}
+
+impl From<Expr> for Box<Spanned<Expr>> {
+    fn from(node: Expr) -> Self {
+        Box::new(node.into())
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Spanned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.node.partial_cmp(&other.node)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Spanned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.fmt(f)
+    }
+}
+
 #[derive(PartialEq, Clone, Debug, Eq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ident(pub String);
 // Trait implementations for Ident
 impl From<Ident> for Expr {
@@ -19,11 +84,21 @@ impl fmt::Display for Ident {
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stmt {
     Assign(Pattern, Expr),
     Return(Expr),
-    Expr(Expr),
-    Import { source: Expr, name: Pattern },
+    // Leaves the innermost loop early
+    Break,
+    // Skips to the next iteration of the innermost loop
+    Continue,
+    Expr(Spanned<Expr>),
+    // `import name from source as alias`, where the alias is optional
+    Import {
+        source: Expr,
+        name: Pattern,
+        alias: Option<Pattern>,
+    },
 }
 
 impl From<Stmt> for BlockStatement {
@@ -37,53 +112,96 @@ impl fmt::Display for Stmt {
         match self {
             Stmt::Assign(ident, expr) => write!(f, "{} = {}", ident, expr),
             Stmt::Return(expr) => write!(f, "return {}", expr),
-            Stmt::Import { source, name } => write!(f, "import {} from {}", name, source),
+            Stmt::Break => write!(f, "break"),
+            Stmt::Continue => write!(f, "continue"),
+            Stmt::Import {
+                source,
+                name,
+                alias,
+            } => {
+                write!(f, "import {} from {}", name, source)?;
+                if let Some(alias) = alias {
+                    write!(f, " as {}", alias)?;
+                }
+                Ok(())
+            }
             Stmt::Expr(expr) => write!(f, "{}", expr),
         }
     }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
+    Integer(i64),
     Number(f64),
     Ident(Ident),
-    Prefix(String, Box<Expr>),
-    Infix(Box<Expr>, String, Box<Expr>),
+    Prefix(String, Box<Spanned<Expr>>),
+    Infix(Box<Spanned<Expr>>, String, Box<Spanned<Expr>>),
     Member {
-        property: Box<Expr>,
-        object: Box<Expr>,
+        property: Box<Spanned<Expr>>,
+        object: Box<Spanned<Expr>>,
         computed: bool,
     },
+    // Part of an array, like `a[1..3]`
+    Slice {
+        object: Box<Spanned<Expr>>,
+        start: Box<Spanned<Expr>>,
+        end: Box<Spanned<Expr>>,
+    },
     Boolean(bool),
     String(String),
     Symbol(String),
+    Char(char),
+    Null,
+    // Updates an existing binding, like `x = x + 1`
+    // Unlike `let`, which is a statement, this evaluates to the assigned value
+    Assign {
+        name: Ident,
+        value: Box<Spanned<Expr>>,
+    },
+    // Updates part of a hash or array binding, like `a.b = c` or `a[i] = c`
+    // The binding gets an updated copy, so other references to the old value don't change
+    AssignMember {
+        target: Box<Spanned<Expr>>,
+        value: Box<Spanned<Expr>>,
+    },
     If {
-        condition: Box<Expr>,
+        condition: Box<Spanned<Expr>>,
         consequence: BlockStatement,
         alternative: BlockStatement,
     },
+    // Repeats the body while the condition is truthy
+    While {
+        condition: Box<Spanned<Expr>>,
+        body: BlockStatement,
+    },
     Function {
-        parameters: Vec<Ident>,
+        parameters: Vec<Parameter>,
+        // Collects any extra arguments into an array, like `...rest`
+        rest: Option<Ident>,
         body: BlockStatement,
     },
     Call {
-        function: Box<Expr>,
+        function: Box<Spanned<Expr>>,
         arguments: Vec<Expr>,
     },
     Match {
-        condition: Box<Expr>,
-        cases: Vec<(Pattern, BlockStatement)>,
+        condition: Box<Spanned<Expr>>,
+        // Each case can have a guard, like `n if n > 0 -> n`
+        cases: Vec<(Pattern, Option<Expr>, BlockStatement)>,
     },
     // Array data structure
     Array(Vec<Expr>),
     // Hashmap/dictionary/object data structure
     // It's a Vec because of trait constraints
-    Hash(Vec<(Ident, Expr)>),
+    // Identifier keys are literal (`{ name = 'bob' }`), other keys are evaluated
+    Hash(Vec<(Expr, Expr)>),
 }
 
 impl From<Expr> for Stmt {
     fn from(val: Expr) -> Self {
-        Stmt::Expr(val)
+        Stmt::Expr(val.into())
     }
 }
 
@@ -115,6 +233,12 @@ impl From<bool> for Expr {
     }
 }
 
+impl From<i64> for Expr {
+    fn from(val: i64) -> Self {
+        Expr::Integer(val)
+    }
+}
+
 impl From<f64> for Expr {
     fn from(val: f64) -> Self {
         Expr::Number(val)
@@ -125,6 +249,7 @@ impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Expr::Ident(ident) => write!(f, "{}", ident.0),
+            Expr::Integer(num) => write!(f, "{}", num),
             Expr::Number(num) => write!(f, "{}", num),
             Expr::Prefix(op, expr) => write!(f, "({}{})", op, expr),
             Expr::Infix(left, operator, right) => write!(f, "({} {} {})", left, operator, right),
@@ -139,9 +264,14 @@ impl fmt::Display for Expr {
                     write!(f, "{}.({})", of, index)
                 }
             }
+            Expr::Slice { object, start, end } => write!(f, "{}[{}..{}]", object, start, end),
             Expr::Boolean(value) => write!(f, "{}", value),
             Expr::String(value) => write!(f, "'{}'", value),
             Expr::Symbol(value) => write!(f, ":{}", value),
+            Expr::Char(value) => write!(f, "`{}`", value),
+            Expr::Null => write!(f, "null"),
+            Expr::Assign { name, value } => write!(f, "({} = {})", name, value),
+            Expr::AssignMember { target, value } => write!(f, "({} = {})", target, value),
             Expr::If {
                 condition,
                 consequence,
@@ -151,11 +281,24 @@ impl fmt::Display for Expr {
                 "if {} {{\n{}}} else {{\n{}}}",
                 condition, consequence, alternative
             ),
-            Expr::Function { parameters, body } => {
+            Expr::While { condition, body } => {
+                write!(f, "while {} {{\n{}}}", condition, body)
+            }
+            Expr::Function {
+                parameters,
+                rest,
+                body,
+            } => {
                 write!(f, "fn (")?;
                 let mut params = vec![];
-                for param in parameters {
-                    params.push(param.0.clone())
+                for (name, default) in parameters {
+                    match default {
+                        Some(default) => params.push(format!("{} = {}", name, default)),
+                        None => params.push(name.0.clone()),
+                    }
+                }
+                if let Some(rest) = rest {
+                    params.push(format!("...{}", rest));
                 }
                 write!(f, "{}", params.join(","))?;
                 write!(f, ") -> {{{}}}", body)?;
@@ -183,8 +326,12 @@ impl fmt::Display for Expr {
                 let mut out = String::new();
                 out.push_str(&format!("{} :: {{\n", condition));
                 let mut formatted_cases = vec![];
-                for (key, value) in cases {
-                    formatted_cases.push(format!("{} -> {{\n{}}},", key, value))
+                for (key, guard, value) in cases {
+                    match guard {
+                        Some(guard) => formatted_cases
+                            .push(format!("{} if {} -> {{\n{}}},", key, guard, value)),
+                        None => formatted_cases.push(format!("{} -> {{\n{}}},", key, value)),
+                    }
                 }
 
                 out.push_str(&formatted_cases.join("\n"));
@@ -219,6 +366,7 @@ impl Default for BlockStatement {
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockStatement(pub Vec<Stmt>);
 impl BlockStatement {
     pub fn new() -> BlockStatement {
@@ -228,7 +376,7 @@ impl BlockStatement {
         BlockStatement(stmts)
     }
     pub fn expr(expr: Expr) -> BlockStatement {
-        BlockStatement(vec![Stmt::Expr(expr)])
+        BlockStatement(vec![Stmt::Expr(expr.into())])
     }
 }
 
@@ -243,7 +391,11 @@ impl fmt::Display for BlockStatement {
 }
 pub type Program = BlockStatement;
 
+// A function parameter, with an optional default for when the argument is left off
+pub type Parameter = (Ident, Option<Expr>);
+
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pattern {
     String(String),
     Number(f64),
@@ -252,11 +404,31 @@ pub enum Pattern {
     Ident(Ident),
     // Array destructuring
     Array(Vec<Pattern>),
+    // Matches numbers from the start up to (but not including) the end, like `0..10`
+    Range(f64, f64),
+    // Binds the rest of an array, like `...rest`
+    // Only valid as the last item of an array pattern
+    Rest(Ident),
     // Hashmap destructing
     Hash(Vec<(Ident, Option<Ident>)>),
     Nothing,
 }
 
+impl Pattern {
+    /// Every name the pattern binds, in order
+    pub fn bindings(&self) -> Vec<Ident> {
+        match self {
+            Pattern::Ident(ident) | Pattern::Rest(ident) => vec![ident.clone()],
+            Pattern::Array(items) => items.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Hash(items) => items
+                .iter()
+                .map(|(key, alias)| alias.clone().unwrap_or_else(|| key.clone()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
 impl fmt::Display for Pattern {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -266,6 +438,8 @@ impl fmt::Display for Pattern {
             Pattern::Symbol(sym) => write!(f, ":{}", sym),
             Pattern::String(str) => write!(f, "'{}'", str),
             Pattern::Nothing => write!(f, "_"),
+            Pattern::Rest(id) => write!(f, "...{}", id),
+            Pattern::Range(start, end) => write!(f, "{}..{}", start, end),
             Pattern::Array(items) => {
                 let x: Vec<String> = items.iter().map(|item| format!("{}", item)).collect();
                 write!(f, "[ {} ]", x.join(","))
@@ -1,8 +1,33 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, env, fs, rc::Rc};
 
 use std::cell::RefCell;
 
-use super::{object::Object, EvalResult, Evaluator};
+use md5::Md5;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use super::{
+    env::Environment,
+    error::EvalError,
+    object::{sorted_entries, Object},
+    EvalResult, Evaluator,
+};
+use crate::ast::{Expr, Ident};
+
+#[cfg(test)]
+#[path = "./builtins_test.rs"]
+mod builtins_test;
+
+/// Builtins that can reach outside the interpreter, which sandboxed evaluators disable
+pub const IO_BUILTINS: &[&str] = &[
+    "env",
+    "env_all",
+    "read_file",
+    "write_file",
+    "read_bytes",
+    "write_bytes",
+    "list_dir",
+];
 
 pub fn get_builtins<'a>() -> HashMap<String, Object<'a>> {
     let mut builtins = HashMap::new();
@@ -12,11 +37,71 @@ pub fn get_builtins<'a>() -> HashMap<String, Object<'a>> {
     builtins.insert("init".to_string(), Object::Builtin(1, init));
     builtins.insert("last".to_string(), Object::Builtin(1, last));
     builtins.insert("tail".to_string(), Object::Builtin(1, tail));
+    builtins.insert("take".to_string(), Object::Builtin(2, take));
+    builtins.insert("drop".to_string(), Object::Builtin(2, drop));
+    builtins.insert("slice".to_string(), Object::Builtin(3, slice));
 
     builtins.insert("len".to_string(), Object::Builtin(1, len));
     builtins.insert("log".to_string(), Object::Builtin(-1, log));
+    builtins.insert("inspect".to_string(), Object::Builtin(1, inspect));
 
     builtins.insert("map".to_string(), Object::Builtin(2, map));
+    builtins.insert("build_array".to_string(), Object::Builtin(2, build_array));
+    builtins.insert("repeat".to_string(), Object::Builtin(2, repeat));
+    builtins.insert("tap".to_string(), Object::Builtin(2, tap));
+    builtins.insert("find".to_string(), Object::Builtin(2, find));
+    builtins.insert("any".to_string(), Object::Builtin(2, any));
+    builtins.insert("all".to_string(), Object::Builtin(2, all));
+
+    // Function helpers
+    builtins.insert("identity".to_string(), Object::Builtin(1, identity));
+    builtins.insert("constant".to_string(), Object::Builtin(1, constant));
+    builtins.insert("flip".to_string(), Object::Builtin(1, flip));
+
+    // Number functions
+    builtins.insert("is_nan".to_string(), Object::Builtin(1, is_nan));
+
+    // Hash functions
+    builtins.insert("keys".to_string(), Object::Builtin(1, keys));
+    builtins.insert("values".to_string(), Object::Builtin(1, values));
+    builtins.insert("entries".to_string(), Object::Builtin(1, entries));
+
+    // Binding functions
+    builtins.insert("unset".to_string(), Object::Builtin(1, unset));
+
+    // String functions
+    builtins.insert("matches".to_string(), Object::Builtin(2, matches));
+    builtins.insert("find_all".to_string(), Object::Builtin(2, find_all));
+    builtins.insert("split_regex".to_string(), Object::Builtin(2, split_regex));
+    builtins.insert(
+        "replace_regex".to_string(),
+        Object::Builtin(3, replace_regex),
+    );
+
+    // Byte functions
+    builtins.insert("encode".to_string(), Object::Builtin(2, encode));
+    builtins.insert("decode".to_string(), Object::Builtin(2, decode));
+    builtins.insert(
+        "base64_encode".to_string(),
+        Object::Builtin(1, base64_encode),
+    );
+    builtins.insert(
+        "base64_decode".to_string(),
+        Object::Builtin(1, base64_decode),
+    );
+    builtins.insert("hex_encode".to_string(), Object::Builtin(1, hex_encode));
+    builtins.insert("hex_decode".to_string(), Object::Builtin(1, hex_decode));
+    builtins.insert("sha256".to_string(), Object::Builtin(1, sha256));
+    builtins.insert("md5".to_string(), Object::Builtin(1, md5));
+
+    // System functions
+    builtins.insert("env".to_string(), Object::Builtin(1, env));
+    builtins.insert("env_all".to_string(), Object::Builtin(0, env_all));
+    builtins.insert("read_file".to_string(), Object::Builtin(1, read_file));
+    builtins.insert("write_file".to_string(), Object::Builtin(2, write_file));
+    builtins.insert("read_bytes".to_string(), Object::Builtin(1, read_bytes));
+    builtins.insert("write_bytes".to_string(), Object::Builtin(2, write_bytes));
+    builtins.insert("list_dir".to_string(), Object::Builtin(1, list_dir));
 
     builtins
 }
@@ -25,16 +110,14 @@ fn len<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
     let arg = args[0].clone();
     let len = match arg {
         Object::Array(arr) => arr.len(),
-        Object::String(s) => s.len(),
+        // Counts characters, so `len('café')` is 4 even though it takes 5 bytes
+        Object::String(s) => s.chars().count(),
+        Object::Bytes(bytes) => bytes.len(),
         Object::Hash(hash) => hash.len(),
-        Object::Function {
-            parameters,
-            body: _,
-            env: _,
-        } => parameters.len(),
+        Object::Function { parameters, .. } => parameters.len(),
         _ => 0,
     };
-    Ok(Object::Number(len as f64))
+    Ok(Object::Integer(len as i64))
 }
 
 fn log<'a>(args: Vec<Object>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
@@ -44,17 +127,49 @@ fn log<'a>(args: Vec<Object>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
     Ok(Object::Void)
 }
 
+// Like log, but gives back its argument so it can sit in the middle of a pipeline
+fn inspect<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    println!("{}", args[0]);
+    Ok(args[0].clone())
+}
+
 fn init<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
     if let Object::Array(array) = args[0].clone() {
-        return Ok(Object::Array(array[0..array.len() - 1].to_vec()));
+        let end = array.len().saturating_sub(1);
+        return Ok(Object::Array(array[0..end].to_vec()));
     };
-    Err(format!("{} isn't an array", args[0]))
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't an array",
+        args[0]
+    )))
 }
 fn tail<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
     if let Object::Array(array) = args[0].clone() {
-        return Ok(Object::Array(array[1..array.len()].to_vec()));
+        return Ok(Object::Array(array.into_iter().skip(1).collect()));
     };
-    Err(format!("{} isn't an array", args[0]))
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't an array",
+        args[0]
+    )))
+}
+
+// Like `array[0..n]`, so `n` is clamped to the array
+fn take<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    eval.borrow()
+        .slice(args[0].clone(), Object::Integer(0), args[1].clone())
+}
+// Everything after the first `n` items, or nothing if there aren't that many
+fn drop<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    eval.borrow().slice(
+        args[0].clone(),
+        args[1].clone(),
+        Object::Number(f64::INFINITY),
+    )
+}
+// Like `array[start..end]`
+fn slice<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    eval.borrow()
+        .slice(args[0].clone(), args[1].clone(), args[2].clone())
 }
 
 fn head<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
@@ -64,7 +179,10 @@ fn head<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a>
             None => Ok(Object::Null),
         };
     }
-    Err(format!("{} isn't an array", args[0]))
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't an array",
+        args[0]
+    )))
 }
 
 fn last<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
@@ -74,22 +192,16 @@ fn last<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a>
             None => Ok(Object::Null),
         };
     }
-    Err(format!("{} isn't an array", args[0]))
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't an array",
+        args[0]
+    )))
 }
 
 fn map<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
     if let Object::Array(array) = args[0].clone() {
         match args[1].clone() {
-            Object::Function {
-                body,
-                env,
-                parameters,
-            } => {
-                let function = Object::Function {
-                    body,
-                    env,
-                    parameters,
-                };
+            function @ (Object::Function { .. } | Object::Builtin(..)) => {
                 let mut arr = vec![];
                 for element in array {
                     let res = eval
@@ -99,8 +211,489 @@ fn map<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResul
                 }
                 return Ok(Object::Array(arr));
             }
-            arg => return Err(format!("Expected function, got {}", arg)),
+            arg => {
+                return Err(EvalError::TypeMismatch(format!(
+                    "Expected function, got {}",
+                    arg
+                )))
+            }
+        }
+    }
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't an array",
+        args[0]
+    )))
+}
+
+// The first item where calling `predicate` gives `matching`, stopping as soon as one is found
+fn find_first<'a>(
+    array: &Object<'a>,
+    predicate: &Object<'a>,
+    matching: bool,
+    eval: &Rc<RefCell<Evaluator<'a>>>,
+) -> Result<Option<Object<'a>>, EvalError> {
+    let array = match array {
+        Object::Array(array) => array,
+        _ => return Err(EvalError::TypeMismatch(format!("{} isn't an array", array))),
+    };
+    for element in array {
+        let res = eval
+            .borrow_mut()
+            .eval_function_call(predicate.clone(), vec![element.clone()])?;
+        if Evaluator::is_truthy(res) == matching {
+            return Ok(Some(element.clone()));
+        }
+    }
+    Ok(None)
+}
+fn find<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    Ok(find_first(&args[0], &args[1], true, &eval)?.unwrap_or(Object::Null))
+}
+fn any<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    Ok(Object::from(
+        find_first(&args[0], &args[1], true, &eval)?.is_some(),
+    ))
+}
+fn all<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    Ok(Object::from(
+        find_first(&args[0], &args[1], false, &eval)?.is_none(),
+    ))
+}
+
+// Builds an array of `len` items by calling a function with each index
+fn build_array<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    let len = match args[0].as_number() {
+        Some(len) if len < 0.0 => {
+            return Err(EvalError::Runtime(format!(
+                "Can't build an array with negative length {}",
+                len
+            )))
+        }
+        Some(len) if len.fract() == 0.0 => len as usize,
+        _ => {
+            return Err(EvalError::TypeMismatch(format!(
+                "{} isn't a valid array length",
+                args[0]
+            )))
+        }
+    };
+    eval.borrow().check_collection_size(len)?;
+    let mut arr = Vec::with_capacity(len);
+    for index in 0..len {
+        let res = eval
+            .borrow_mut()
+            .eval_function_call(args[1].clone(), vec![Object::Integer(index as i64)])?;
+        arr.push(res);
+    }
+    Ok(Object::Array(arr))
+}
+
+// Builds an array holding `count` copies of a value
+fn repeat<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    let count = match args[1].as_number() {
+        Some(count) if count >= 0.0 && count.fract() == 0.0 => count as usize,
+        _ => {
+            return Err(EvalError::TypeMismatch(format!(
+                "{} isn't a valid repeat count",
+                args[1]
+            )))
+        }
+    };
+    eval.borrow().check_collection_size(count)?;
+    Ok(Object::Array(vec![args[0].clone(); count]))
+}
+
+// Calls a function with a value for its side effects, then passes the value along
+// Handy for peeking at the middle of a `|>` chain
+fn tap<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    eval.borrow_mut()
+        .eval_function_call(args[1].clone(), vec![args[0].clone()])?;
+    Ok(args[0].clone())
+}
+
+fn identity<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    Ok(args[0].clone())
+}
+
+// Returns a function that ignores its argument and always gives back the captured value
+fn constant<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let mut env = Environment::new();
+    env.set("value".to_string(), args[0].clone());
+    Ok(Object::Function {
+        parameters: vec![(Ident::from("_"), None)],
+        rest: None,
+        body: Expr::Ident(Ident::from("value")).into(),
+        env: env.into(),
+    })
+}
+
+// Wraps a two argument function in one that takes its arguments the other way around
+fn flip<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    if !matches!(args[0], Object::Function { .. } | Object::Builtin(..)) {
+        return Err(EvalError::TypeMismatch(format!(
+            "Expected function, got {}",
+            args[0]
+        )));
+    }
+    let mut env = Environment::new();
+    env.set("function".to_string(), args[0].clone());
+    Ok(Object::Function {
+        parameters: vec![(Ident::from("a"), None), (Ident::from("b"), None)],
+        rest: None,
+        body: Expr::Call {
+            function: Expr::Ident(Ident::from("function")).into(),
+            arguments: vec![Expr::Ident(Ident::from("b")), Expr::Ident(Ident::from("a"))],
+        }
+        .into(),
+        env: env.into(),
+    })
+}
+
+// NaN never equals anything, even itself, so `x == x` can't be used to check for it
+fn is_nan<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    match args[0] {
+        Object::Number(value) => Ok(Object::from(value.is_nan())),
+        Object::Integer(_) => Ok(Object::from(false)),
+        _ => Err(EvalError::TypeMismatch(format!(
+            "{} isn't a number",
+            args[0]
+        ))),
+    }
+}
+
+// Removes a binding from the current scope, giving back its value or null if it wasn't bound
+fn unset<'a>(args: Vec<Object<'a>>, eval: Rc<RefCell<Evaluator<'a>>>) -> EvalResult<'a> {
+    let name = match &args[0] {
+        Object::Symbol(name) | Object::String(name) => name,
+        _ => {
+            return Err(EvalError::TypeMismatch(format!(
+                "{} isn't a symbol or string",
+                args[0]
+            )))
+        }
+    };
+    let removed = eval.borrow().env.borrow_mut().delete(name);
+    Ok(removed.unwrap_or(Object::Null))
+}
+
+// Hash builtins all walk entries in the order from `sorted_entries`
+fn keys<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    match &args[0] {
+        Object::Hash(hash) => Ok(Object::Array(
+            sorted_entries(hash)
+                .into_iter()
+                .map(|(key, _)| key.clone())
+                .collect(),
+        )),
+        arg => Err(EvalError::TypeMismatch(format!("{} isn't a hash", arg))),
+    }
+}
+
+fn values<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    match &args[0] {
+        Object::Hash(hash) => Ok(Object::Array(
+            sorted_entries(hash)
+                .into_iter()
+                .map(|(_, value)| value.clone())
+                .collect(),
+        )),
+        arg => Err(EvalError::TypeMismatch(format!("{} isn't a hash", arg))),
+    }
+}
+
+// Entries are [key, value] pairs
+fn entries<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    match &args[0] {
+        Object::Hash(hash) => Ok(Object::Array(
+            sorted_entries(hash)
+                .into_iter()
+                .map(|(key, value)| Object::Array(vec![key.clone(), value.clone()]))
+                .collect(),
+        )),
+        arg => Err(EvalError::TypeMismatch(format!("{} isn't a hash", arg))),
+    }
+}
+
+// Stands in for IO builtins in sandboxed mode
+pub fn disabled<'a>(_: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    Err(EvalError::Runtime(
+        "This builtin can't be used in sandboxed mode".to_string(),
+    ))
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| format!("Invalid regex pattern '{}':\n{}", pattern, err))
+}
+
+// Pulls the (string, pattern) pair most regex builtins take
+fn string_and_regex<'a>(args: &[Object<'a>]) -> Result<(String, Regex), EvalError> {
+    match (args[0].clone(), args[1].clone()) {
+        (Object::String(string), Object::String(pattern)) => Ok((string, compile_regex(&pattern)?)),
+        (Object::String(_), pattern) => Err(EvalError::TypeMismatch(format!(
+            "{} isn't a string pattern",
+            pattern
+        ))),
+        (string, _) => Err(EvalError::TypeMismatch(format!(
+            "{} isn't a string",
+            string
+        ))),
+    }
+}
+
+fn matches<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let (string, regex) = string_and_regex(&args)?;
+    Ok(Object::Boolean(regex.is_match(&string)))
+}
+
+fn find_all<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let (string, regex) = string_and_regex(&args)?;
+    let found = regex
+        .find_iter(&string)
+        .map(|m| Object::String(m.as_str().to_string()))
+        .collect();
+    Ok(Object::Array(found))
+}
+
+fn split_regex<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let (string, regex) = string_and_regex(&args)?;
+    let parts = regex
+        .split(&string)
+        .map(|part| Object::String(part.to_string()))
+        .collect();
+    Ok(Object::Array(parts))
+}
+
+// Replacements can reference capture groups with $1, $2, or ${name}
+fn replace_regex<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let (string, regex) = string_and_regex(&args)?;
+    let replacement = match args[2].clone() {
+        Object::String(replacement) => replacement,
+        arg => return Err(EvalError::TypeMismatch(format!("{} isn't a string", arg))),
+    };
+    let replaced = regex.replace_all(&string, replacement.as_str());
+    Ok(Object::String(replaced.to_string()))
+}
+
+// Supported encodings are utf8, latin1, and ascii
+fn encoding_name<'a>(arg: &Object<'a>) -> Result<String, EvalError> {
+    match arg {
+        Object::String(name) => match name.to_lowercase().replace('-', "").as_str() {
+            encoding @ ("utf8" | "latin1" | "ascii") => Ok(encoding.to_string()),
+            _ => Err(EvalError::Runtime(format!(
+                "Unknown encoding '{}', expected 'utf8', 'latin1', or 'ascii'",
+                name
+            ))),
+        },
+        arg => Err(EvalError::TypeMismatch(format!("{} isn't a string", arg))),
+    }
+}
+
+// Turns a string into bytes, like `encode('hi', 'utf8')`
+fn encode<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let string = match &args[0] {
+        Object::String(string) => string,
+        arg => return Err(EvalError::TypeMismatch(format!("{} isn't a string", arg))),
+    };
+    let limit = match encoding_name(&args[1])?.as_str() {
+        "utf8" => return Ok(Object::Bytes(string.as_bytes().to_vec())),
+        "latin1" => 0xff,
+        _ => 0x7f,
+    };
+    let mut bytes = Vec::with_capacity(string.len());
+    for c in string.chars() {
+        if c as u32 > limit {
+            return Err(EvalError::Runtime(format!(
+                "Can't encode '{}' as {}",
+                c, args[1]
+            )));
+        }
+        bytes.push(c as u8);
+    }
+    Ok(Object::Bytes(bytes))
+}
+
+// Turns bytes back into a string, erroring if they aren't valid in the encoding
+fn decode<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let bytes = match &args[0] {
+        Object::Bytes(bytes) => bytes,
+        arg => return Err(EvalError::TypeMismatch(format!("{} isn't bytes", arg))),
+    };
+    let string = match encoding_name(&args[1])?.as_str() {
+        "utf8" => String::from_utf8(bytes.clone())
+            .map_err(|err| EvalError::Runtime(format!("Bytes aren't valid utf8: {}", err)))?,
+        "latin1" => bytes.iter().map(|byte| *byte as char).collect(),
+        _ => {
+            if let Some(byte) = bytes.iter().find(|byte| !byte.is_ascii()) {
+                return Err(EvalError::Runtime(format!(
+                    "Bytes aren't valid ascii: found byte {:02x}",
+                    byte
+                )));
+            }
+            bytes.iter().map(|byte| *byte as char).collect()
+        }
+    };
+    Ok(Object::String(string))
+}
+
+// Encoders take a string (as utf8) or bytes
+fn bytes_of<'o>(arg: &'o Object) -> Result<&'o [u8], EvalError> {
+    match arg {
+        Object::String(string) => Ok(string.as_bytes()),
+        Object::Bytes(bytes) => Ok(bytes),
+        arg => Err(EvalError::TypeMismatch(format!(
+            "{} isn't a string or bytes",
+            arg
+        ))),
+    }
+}
+
+// Decoders take the encoded string and give back bytes
+fn encoded_string<'o>(arg: &'o Object) -> Result<&'o str, EvalError> {
+    match arg {
+        Object::String(string) => Ok(string),
+        arg => Err(EvalError::TypeMismatch(format!("{} isn't a string", arg))),
+    }
+}
+
+fn base64_encode<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    Ok(Object::String(base64::encode(bytes_of(&args[0])?)))
+}
+
+fn base64_decode<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let decoded = base64::decode(encoded_string(&args[0])?)
+        .map_err(|err| EvalError::Runtime(format!("Invalid base64 {}: {}", args[0], err)))?;
+    Ok(Object::Bytes(decoded))
+}
+
+fn hex_encode<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    Ok(Object::String(hex::encode(bytes_of(&args[0])?)))
+}
+
+fn hex_decode<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    let decoded = hex::decode(encoded_string(&args[0])?)
+        .map_err(|err| EvalError::Runtime(format!("Invalid hex {}: {}", args[0], err)))?;
+    Ok(Object::Bytes(decoded))
+}
+
+// Digests are returned as lowercase hex strings
+fn sha256<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    Ok(Object::String(hex::encode(Sha256::digest(bytes_of(
+        &args[0],
+    )?))))
+}
+
+fn md5<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    Ok(Object::String(hex::encode(Md5::digest(bytes_of(
+        &args[0],
+    )?))))
+}
+
+fn env<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    if let Object::String(name) = args[0].clone() {
+        return match env::var(name) {
+            Ok(value) => Ok(Object::String(value)),
+            Err(_) => Ok(Object::Null),
+        };
+    }
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't a string",
+        args[0]
+    )))
+}
+
+fn env_all<'a>(_: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    #[allow(clippy::mutable_key_type)]
+    let vars = env::vars()
+        .map(|(name, value)| (Object::String(name), Object::String(value)))
+        .collect();
+    Ok(Object::Hash(vars))
+}
+
+// Fallible builtins return results like [:success, value] or [:error, message],
+// so scripts can match on them instead of aborting
+fn success(value: Object) -> Object {
+    Object::Array(vec![Object::Symbol("success".to_string()), value])
+}
+fn failure<'a, T: ToString>(message: T) -> Object<'a> {
+    Object::Array(vec![
+        Object::Symbol("error".to_string()),
+        Object::String(message.to_string()),
+    ])
+}
+
+fn read_file<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    if let Object::String(path) = args[0].clone() {
+        return Ok(match fs::read_to_string(path) {
+            Ok(contents) => success(Object::String(contents)),
+            Err(err) => failure(err),
+        });
+    }
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't a string",
+        args[0]
+    )))
+}
+
+fn write_file<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    match (args[0].clone(), args[1].clone()) {
+        (Object::String(path), Object::String(contents)) => Ok(match fs::write(path, contents) {
+            Ok(()) => success(Object::Null),
+            Err(err) => failure(err),
+        }),
+        (Object::String(_), contents) => Err(EvalError::TypeMismatch(format!(
+            "{} isn't a string",
+            contents
+        ))),
+        (path, _) => Err(EvalError::TypeMismatch(format!("{} isn't a string", path))),
+    }
+}
+
+fn read_bytes<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    if let Object::String(path) = args[0].clone() {
+        return Ok(match fs::read(path) {
+            Ok(contents) => success(Object::Bytes(contents)),
+            Err(err) => failure(err),
+        });
+    }
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't a string",
+        args[0]
+    )))
+}
+
+fn write_bytes<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    match (args[0].clone(), args[1].clone()) {
+        (Object::String(path), Object::Bytes(contents)) => Ok(match fs::write(path, contents) {
+            Ok(()) => success(Object::Null),
+            Err(err) => failure(err),
+        }),
+        (Object::String(_), contents) => {
+            Err(EvalError::TypeMismatch(format!("{} isn't bytes", contents)))
+        }
+        (path, _) => Err(EvalError::TypeMismatch(format!("{} isn't a string", path))),
+    }
+}
+
+// Entry names are sorted, since the OS doesn't guarantee an order
+fn list_dir<'a>(args: Vec<Object<'a>>, _: Rc<RefCell<Evaluator>>) -> EvalResult<'a> {
+    if let Object::String(path) = args[0].clone() {
+        let entries = match fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => return Ok(failure(format!("Can't list {}: {}", path, err))),
+        };
+        let mut names = vec![];
+        for entry in entries {
+            match entry {
+                Ok(entry) => names.push(entry.file_name().to_string_lossy().to_string()),
+                Err(err) => return Ok(failure(err)),
+            }
         }
+        names.sort();
+        let names = names.into_iter().map(Object::String).collect();
+        return Ok(success(Object::Array(names)));
     }
-    Err(format!("{} isn't an array", args[0]))
+    Err(EvalError::TypeMismatch(format!(
+        "{} isn't a string",
+        args[0]
+    )))
 }
@@ -0,0 +1,550 @@
+use std::env;
+
+use crate::evaluation::config::EvaluatorConfig;
+use crate::evaluation::evaluation_test::{eval, eval_with_config, test_error, test_output};
+use crate::evaluation::object::Object;
+
+fn strings(items: &[&str]) -> Object<'static> {
+    Object::Array(
+        items
+            .iter()
+            .map(|item| Object::String(item.to_string()))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_matches() {
+    test_output("matches('abc123', '[0-9]+')", Object::Boolean(true));
+    test_output("matches('abcdef', '[0-9]+')", Object::Boolean(false));
+}
+
+#[test]
+fn test_find_all() {
+    test_output(
+        "find_all('a1b22c333', '[0-9]+')",
+        strings(&["1", "22", "333"]),
+    );
+    test_output("find_all('abc', '[0-9]+')", strings(&[]));
+}
+
+#[test]
+fn test_split_regex() {
+    test_output("split_regex('a b\t  c', '\\s+')", strings(&["a", "b", "c"]));
+    test_output("split_regex('a,b;c', '[,;]')", strings(&["a", "b", "c"]));
+}
+
+#[test]
+fn test_replace_regex() {
+    test_output(
+        "replace_regex('2021-06-15', '([0-9]+)-([0-9]+)-([0-9]+)', '$3/$2/$1')",
+        Object::String("15/06/2021".to_string()),
+    );
+    test_output(
+        "replace_regex('hello', '[0-9]', 'x')",
+        Object::String("hello".to_string()),
+    );
+}
+
+#[test]
+fn test_invalid_regex() {
+    let err = test_error("matches('abc', '[0-9')");
+    assert!(err.contains("Invalid regex pattern"));
+    let err = test_error("find_all('abc', '(')");
+    assert!(err.contains("Invalid regex pattern"));
+    let err = test_error("split_regex('abc', '*')");
+    assert!(err.contains("Invalid regex pattern"));
+    let err = test_error("replace_regex('abc', '[', 'x')");
+    assert!(err.contains("Invalid regex pattern"));
+}
+
+#[test]
+fn test_env() {
+    env::set_var("BLISS_TEST_ENV", "hello");
+    test_output("env('BLISS_TEST_ENV')", Object::String("hello".to_string()));
+    test_output("env('BLISS_TEST_UNSET_ENV')", Object::Null);
+    test_output(
+        "env_all()['BLISS_TEST_ENV']",
+        Object::String("hello".to_string()),
+    );
+}
+
+fn temp_path(name: &str) -> String {
+    env::temp_dir()
+        .join(format!("bliss_{}_{}", std::process::id(), name))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn result(tag: &str, value: Object<'static>) -> Object<'static> {
+    Object::Array(vec![Object::Symbol(tag.to_string()), value])
+}
+
+#[test]
+fn test_read_write_file() {
+    let path = temp_path("read_write.txt");
+    test_output(
+        &format!("write_file('{}', 'hello file')", path),
+        result("success", Object::Null),
+    );
+    test_output(
+        &format!("read_file('{}')", path),
+        result("success", Object::String("hello file".to_string())),
+    );
+    test_output(
+        &format!(
+            "read_file('{}') :: {{ [:success, text] -> text, [:error, _] -> 'failed' }}",
+            path
+        ),
+        Object::String("hello file".to_string()),
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_read_missing_file() {
+    let path = temp_path("missing.txt");
+    test_output(
+        &format!(
+            "read_file('{}') :: {{ [:success, _] -> 'read', [:error, _] -> 'failed' }}",
+            path
+        ),
+        Object::String("failed".to_string()),
+    );
+}
+
+#[test]
+fn test_read_write_bytes() {
+    let path = temp_path("bytes.bin");
+    test_output(
+        &format!("write_bytes('{}', encode('héllo', 'utf8'))", path),
+        result("success", Object::Null),
+    );
+    assert_eq!(std::fs::read(&path).unwrap(), "héllo".as_bytes());
+    test_output(
+        &format!(
+            "read_bytes('{}') :: {{ [:success, bytes] -> decode(bytes, 'utf8'), _ -> null }}",
+            path
+        ),
+        Object::String("héllo".to_string()),
+    );
+
+    // Binary data doesn't have to be valid text
+    std::fs::write(&path, [0xff, 0x00, 0x10]).unwrap();
+    test_output(
+        &format!("read_bytes('{}')", path),
+        result("success", Object::Bytes(vec![0xff, 0x00, 0x10])),
+    );
+    let err = test_error(&format!(
+        "read_bytes('{}') :: {{ [:success, bytes] -> decode(bytes, 'utf8') }}",
+        path
+    ));
+    assert!(err.contains("aren't valid utf8"), "{}", err);
+    std::fs::remove_file(path).unwrap();
+
+    let err = test_error("write_bytes('nope.bin', 'text')");
+    assert_eq!(err, "'text' isn't bytes");
+}
+
+#[test]
+fn test_encodings() {
+    test_output("encode('é', 'utf8')", Object::Bytes(vec![0xc3, 0xa9]));
+    test_output("encode('é', 'latin1')", Object::Bytes(vec![0xe9]));
+    test_output(
+        "decode(encode('é', 'latin1'), 'latin1')",
+        Object::String("é".to_string()),
+    );
+    test_output("len(encode('é', 'utf-8'))", Object::Number(2.0));
+    test_output(
+        "encode('a', 'utf8') == encode('a', 'ascii')",
+        Object::Boolean(true),
+    );
+
+    let err = test_error("encode('é', 'ascii')");
+    assert!(err.contains("Can't encode 'é'"), "{}", err);
+    let err = test_error("decode(encode('é', 'latin1'), 'ascii')");
+    assert!(err.contains("found byte e9"), "{}", err);
+    let err = test_error("encode('a', 'utf16')");
+    assert!(err.contains("Unknown encoding"), "{}", err);
+}
+
+#[test]
+fn test_base64() {
+    test_output(
+        "base64_encode('hello world')",
+        Object::String("aGVsbG8gd29ybGQ=".to_string()),
+    );
+    test_output(
+        "base64_encode(hex_decode('ff00'))",
+        Object::String("/wA=".to_string()),
+    );
+    test_output(
+        "decode(base64_decode(base64_encode('héllo')), 'utf8')",
+        Object::String("héllo".to_string()),
+    );
+    test_output("base64_decode('')", Object::Bytes(vec![]));
+
+    for input in &["base64_decode('not base64!')", "base64_decode('a')"] {
+        let err = test_error(input);
+        assert!(err.contains("Invalid base64"), "{}", err);
+    }
+    let err = test_error("base64_encode(5)");
+    assert_eq!(err, "5 isn't a string or bytes");
+}
+
+#[test]
+fn test_hex() {
+    test_output("hex_encode('hi')", Object::String("6869".to_string()));
+    test_output(
+        "hex_decode('DEADbeef')",
+        Object::Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+    );
+    test_output(
+        "hex_decode(hex_encode(encode('é', 'latin1')))",
+        Object::Bytes(vec![0xe9]),
+    );
+
+    for input in &["hex_decode('abc')", "hex_decode('zz')"] {
+        let err = test_error(input);
+        assert!(err.contains("Invalid hex"), "{}", err);
+    }
+    let err = test_error("hex_decode(encode('ab', 'utf8'))");
+    assert!(err.contains("isn't a string"), "{}", err);
+}
+
+#[test]
+fn test_digests() {
+    // Vectors from FIPS 180-2 and RFC 1321
+    test_output(
+        "sha256('abc')",
+        Object::String(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+        ),
+    );
+    test_output(
+        "sha256('')",
+        Object::String(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string(),
+        ),
+    );
+    test_output(
+        "md5('abc')",
+        Object::String("900150983cd24fb0d6963f7d28e17f72".to_string()),
+    );
+    test_output(
+        "md5('message digest')",
+        Object::String("f96b697d7cb7938d525a2f31aaf161d0".to_string()),
+    );
+    // Bytes hash the same as the string they encode
+    test_output(
+        "sha256(encode('abc', 'utf8')) == sha256('abc')",
+        Object::Boolean(true),
+    );
+    let err = test_error("md5(1)");
+    assert_eq!(err, "1 isn't a string or bytes");
+}
+
+#[test]
+fn test_bytes_display() {
+    assert_eq!(Object::Bytes(vec![0x68, 0x69]).to_string(), "<bytes 68 69>");
+    assert_eq!(
+        Object::Bytes((0..10).collect()).to_string(),
+        "<bytes 00 01 02 03 04 05 06 07 ... (10 total)>"
+    );
+}
+
+#[test]
+fn test_list_dir() {
+    let dir = temp_path("list_dir");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(format!("{}/b.txt", dir), "").unwrap();
+    std::fs::write(format!("{}/a.txt", dir), "").unwrap();
+    std::fs::create_dir_all(format!("{}/nested", dir)).unwrap();
+
+    test_output(
+        &format!("list_dir('{}')", dir),
+        result("success", strings(&["a.txt", "b.txt", "nested"])),
+    );
+    test_output(
+        &format!(
+            "list_dir('{}/a.txt') :: {{ [:success, _] -> 'listed', [:error, _] -> 'failed' }}",
+            dir
+        ),
+        Object::String("failed".to_string()),
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn numbers(items: &[f64]) -> Object<'static> {
+    Object::Array(items.iter().map(|item| Object::Number(*item)).collect())
+}
+
+#[test]
+fn test_build_array() {
+    test_output(
+        "build_array(5, fn i -> i * i)",
+        numbers(&[0.0, 1.0, 4.0, 9.0, 16.0]),
+    );
+    test_output("build_array(0, fn i -> i)", numbers(&[]));
+}
+
+#[test]
+fn test_len() {
+    test_output("len('cafe')", Object::Number(4.0));
+    test_output("len('café')", Object::Number(4.0));
+    test_output("len('日本')", Object::Number(2.0));
+    test_output("len([1, 2, 3])", Object::Number(3.0));
+}
+
+#[test]
+fn test_init_and_tail() {
+    test_output("init([1, 2, 3])", numbers(&[1.0, 2.0]));
+    test_output("tail([1, 2, 3])", numbers(&[2.0, 3.0]));
+    test_output("init([])", numbers(&[]));
+    test_output("tail([])", numbers(&[]));
+}
+
+#[test]
+fn test_take_drop_slice() {
+    test_output("take([1, 2, 3], 2)", numbers(&[1.0, 2.0]));
+    test_output("take([1, 2], 5)", numbers(&[1.0, 2.0]));
+    test_output("take([1, 2], -1)", numbers(&[]));
+    test_output("drop([1, 2, 3], 1)", numbers(&[2.0, 3.0]));
+    test_output("drop([1, 2, 3], 5)", numbers(&[]));
+    test_output("drop([1, 2], -1)", numbers(&[1.0, 2.0]));
+    test_output("slice([1, 2, 3, 4], 1, 3)", numbers(&[2.0, 3.0]));
+    test_output(
+        "slice([1, 2, 3, 4], -5, 50)",
+        numbers(&[1.0, 2.0, 3.0, 4.0]),
+    );
+    test_output("[] |> take(_, 3)", numbers(&[]));
+
+    let err = test_error("take('abc', 1)");
+    assert!(err.contains("Can't slice"), "{}", err);
+    let err = test_error("drop([1], 'a')");
+    assert!(err.contains("Can't slice"), "{}", err);
+}
+
+#[test]
+fn test_find_any_all() {
+    test_output("find([1, 2, 3, 4], fn x -> x > 2)", Object::Number(3.0));
+    test_output("find([1, 2], fn x -> x > 2)", Object::Null);
+    test_output("any([1, 2, 3], fn x -> x == 2)", Object::Boolean(true));
+    test_output("any([1, 2, 3], fn x -> x == 5)", Object::Boolean(false));
+    test_output("any([], fn x -> true)", Object::Boolean(false));
+    test_output("all([1, 2, 3], fn x -> x > 0)", Object::Boolean(true));
+    test_output("all([1, 2, 3], fn x -> x > 1)", Object::Boolean(false));
+    test_output("all([], fn x -> false)", Object::Boolean(true));
+
+    // Stops calling the predicate once the answer is known
+    test_output(
+        "let calls = 0; any([1, 2, 3], fn x -> { calls += 1; x == 1 }); calls",
+        Object::Number(1.0),
+    );
+
+    let err = test_error("any(1, fn x -> true)");
+    assert!(err.contains("isn't an array"), "{}", err);
+}
+
+#[test]
+fn test_unset() {
+    test_output("let x = 1; unset(:x)", Object::Number(1.0));
+    test_output("unset('missing')", Object::Null);
+    let err = test_error("let x = 1; unset(:x); x");
+    assert!(err.contains("Identifier not found: x"), "{}", err);
+    // Only the current scope is touched, so the outer x shows through again
+    test_output(
+        "let x = 1; let f = fn () -> { let x = 2; unset(:x); x }; [f(), x]",
+        numbers(&[1.0, 1.0]),
+    );
+    let err = test_error("unset(1)");
+    assert!(err.contains("isn't a symbol or string"), "{}", err);
+}
+
+#[test]
+fn test_inspect() {
+    test_output("inspect(5) + 1", Object::Number(6.0));
+    test_output("[1, 2] |> inspect |> len", Object::Number(2.0));
+    let err = test_error("inspect(1, 2)");
+    assert!(err.contains("expected 1, found 2"), "{}", err);
+}
+
+#[test]
+fn test_is_nan() {
+    test_output("is_nan((-1) ** 0.5)", Object::Boolean(true));
+    test_output("is_nan(1)", Object::Boolean(false));
+    test_output("is_nan(-0)", Object::Boolean(false));
+    let err = test_error("is_nan('a')");
+    assert!(err.contains("isn't a number"), "{}", err);
+}
+
+#[test]
+fn test_tap() {
+    let path = temp_path("tap.txt");
+    test_output(
+        &format!(
+            "let xs = [1, 2] |> tap(_, fn xs -> write_file('{}', 'tapped')) |> map(_, fn x -> x * 10);
+            [xs, read_file('{}')]",
+            path, path
+        ),
+        Object::Array(vec![
+            numbers(&[10.0, 20.0]),
+            result("success", Object::String("tapped".to_string())),
+        ]),
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_identity_and_constant() {
+    test_output("5 |> identity", Object::Number(5.0));
+    test_output("[1, 2, 3] |> map(_, identity)", numbers(&[1.0, 2.0, 3.0]));
+    test_output(
+        "[1, 2, 3] |> map(_, constant('x'))",
+        strings(&["x", "x", "x"]),
+    );
+    test_output(
+        "let always = constant(:yes); always(null)",
+        Object::Symbol("yes".to_string()),
+    );
+}
+
+#[test]
+fn test_flip() {
+    test_output(
+        "let subtract = fn (a, b) -> a - b; flip(subtract)(10, 3)",
+        Object::Number(-7.0),
+    );
+    test_output(
+        "let subtract = fn (a, b) -> a - b; 3 |> flip(subtract)(_, 10)",
+        Object::Number(7.0),
+    );
+    test_output("flip(build_array)(fn i -> i, 2)", numbers(&[0.0, 1.0]));
+    let err = test_error("flip(5)");
+    assert!(err.contains("Expected function"), "{}", err);
+}
+
+#[test]
+fn test_hash_iteration_order() {
+    let hash = "{ b = 2, 10 = :ten, a = 1, :sym = 3, 2 = :two, true = 4 }";
+    let keys = Object::Array(vec![
+        Object::Number(2.0),
+        Object::Number(10.0),
+        Object::String("a".to_string()),
+        Object::String("b".to_string()),
+        Object::Symbol("sym".to_string()),
+        Object::Boolean(true),
+    ]);
+    let values = Object::Array(vec![
+        Object::Symbol("two".to_string()),
+        Object::Symbol("ten".to_string()),
+        Object::Number(1.0),
+        Object::Number(2.0),
+        Object::Number(3.0),
+        Object::Number(4.0),
+    ]);
+    test_output(&format!("keys({})", hash), keys.clone());
+    test_output(&format!("values({})", hash), values.clone());
+
+    let entries = match eval(&format!("entries({})", hash)).unwrap() {
+        Object::Array(entries) => entries,
+        other => panic!("Expected an array, got {}", other),
+    };
+    let (entry_keys, entry_values): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .map(|entry| match entry {
+            Object::Array(pair) => (pair[0].clone(), pair[1].clone()),
+            other => panic!("Expected a pair, got {}", other),
+        })
+        .unzip();
+    assert_eq!(Object::Array(entry_keys), keys);
+    assert_eq!(Object::Array(entry_values), values);
+
+    assert_eq!(
+        eval(hash).unwrap().to_string(),
+        "{2 = :two, 10 = :ten, a = 1, b = 2, :sym = 3, true = 4}"
+    );
+}
+
+#[test]
+fn test_build_array_errors() {
+    let err = test_error("build_array(-1, fn i -> i)");
+    assert!(err.contains("negative length"), "{}", err);
+    let err = test_error("build_array(1.5, fn i -> i)");
+    assert!(err.contains("isn't a valid array length"), "{}", err);
+    let err = test_error("build_array(2, 5)");
+    assert!(err.contains("Can't call 5"), "{}", err);
+}
+
+#[test]
+fn test_sandboxed() {
+    let sandboxed = EvaluatorConfig {
+        sandboxed: true,
+        ..EvaluatorConfig::default()
+    };
+    let path = temp_path("sandboxed.txt");
+    std::fs::write(&path, "secret").unwrap();
+
+    let err = eval_with_config(&format!("read_file('{}')", path), sandboxed.clone())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("sandboxed mode"), "{}", err);
+    for call in &[
+        "write_file('x', 'y')",
+        "env('HOME')",
+        "env_all()",
+        "list_dir('.')",
+    ] {
+        let err = eval_with_config(call, sandboxed.clone())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("sandboxed mode"), "{}", err);
+    }
+    // Everything else still works
+    assert_eq!(
+        eval_with_config("len([1, 2])", sandboxed),
+        Ok(Object::Number(2.0))
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_repeat() {
+    test_output("repeat('a', 3)", strings(&["a", "a", "a"]));
+    test_output("repeat(1, 0)", numbers(&[]));
+    let err = test_error("repeat(1, -2)");
+    assert!(err.contains("isn't a valid repeat count"), "{}", err);
+}
+
+#[test]
+fn test_max_collection_size() {
+    let config = EvaluatorConfig {
+        max_collection_size: 10,
+        ..EvaluatorConfig::default()
+    };
+    for input in &[
+        "repeat(0, 11)",
+        "build_array(11, fn i -> i)",
+        "0..11",
+        "[1, 2, 3, 4, 5, 6] + [7, 8, 9, 10, 11]",
+    ] {
+        let err = eval_with_config(input, config.clone())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("the limit is 10"), "{}: {}", input, err);
+    }
+    assert_eq!(
+        eval_with_config("len(repeat(0, 10))", config.clone()),
+        Ok(Object::Number(10.0))
+    );
+    assert_eq!(
+        eval_with_config("len(build_array(10, fn i -> i))", config.clone()),
+        Ok(Object::Number(10.0))
+    );
+    assert_eq!(
+        eval_with_config("len(0..10)", config),
+        Ok(Object::Number(10.0))
+    );
+    // The default is generous enough for everyday use
+    test_output("len(repeat(0, 1000))", Object::Number(1000.0));
+}
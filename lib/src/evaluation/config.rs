@@ -0,0 +1,44 @@
+/// Knobs for tuning how an `Evaluator` behaves
+///
+/// Start from `EvaluatorConfig::default()` and override what you need:
+///
+/// ```
+/// use lib::EvaluatorConfig;
+///
+/// let config = EvaluatorConfig {
+///     recursion_limit: 64,
+///     ..EvaluatorConfig::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluatorConfig {
+    /// How many function calls deep evaluation can go before it errors
+    ///
+    /// The evaluator grows its stack on the heap as it needs to, so this guards against runaway
+    /// recursion rather than stack overflows
+    pub recursion_limit: usize,
+    /// Makes `let` error instead of shadowing a builtin, so `let len = 5` can't break later calls
+    pub protect_builtins: bool,
+    /// Disables builtins that touch the outside world (files, environment variables),
+    /// for running untrusted code. Calling one errors instead
+    pub sandboxed: bool,
+    /// The most items a range or builtin like `repeat` can create at once,
+    /// so small inputs can't allocate unbounded amounts of memory
+    pub max_collection_size: usize,
+    /// Counts expressions, calls, and allocations, readable with `Evaluator::stats`
+    ///
+    /// Off by default, since counting adds a little overhead to everything
+    pub collect_stats: bool,
+}
+
+impl Default for EvaluatorConfig {
+    fn default() -> Self {
+        EvaluatorConfig {
+            recursion_limit: 1000,
+            protect_builtins: false,
+            sandboxed: false,
+            max_collection_size: 1_000_000,
+            collect_stats: false,
+        }
+    }
+}
@@ -4,6 +4,10 @@ use std::collections::HashMap;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+#[cfg(test)]
+#[path = "./env_test.rs"]
+mod env_test;
+
 type Env<'a> = Rc<RefCell<Environment<'a>>>;
 
 #[derive(PartialEq, Clone, Debug)]
@@ -51,6 +55,23 @@ impl<'a> Environment<'a> {
         self.store.insert(key, value);
     }
 
+    // Updates the closest existing binding, returning false if there isn't one
+    pub fn assign(&mut self, key: String, value: Object<'a>) -> bool {
+        if let Some(slot) = self.store.get_mut(&key) {
+            *slot = value;
+            return true;
+        }
+        match self.parent {
+            Some(ref parent) => parent.borrow_mut().assign(key, value),
+            None => false,
+        }
+    }
+
+    // Removes a binding from this scope only, so a shadowed binding in a parent shows through again
+    pub fn delete(&mut self, key: &str) -> Option<Object<'a>> {
+        self.store.remove(key)
+    }
+
     pub fn has(&self, key: &str) -> bool {
         self.store.contains_key(key)
     }
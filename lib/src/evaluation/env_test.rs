@@ -0,0 +1,27 @@
+use super::*;
+
+#[test]
+fn test_delete() {
+    let mut env = Environment::new();
+    env.set("x".to_string(), Object::Number(1.0));
+    assert_eq!(env.delete("x"), Some(Object::Number(1.0)));
+    assert_eq!(env.get("x".to_string()), None);
+    assert_eq!(env.delete("x"), None);
+    assert_eq!(env.delete("missing"), None);
+}
+
+#[test]
+fn test_delete_shadowed() {
+    let parent: Env = Environment::new().into();
+    parent
+        .borrow_mut()
+        .set("x".to_string(), Object::Number(1.0));
+    let mut child = Environment::new_enclosed(&parent);
+    child.set("x".to_string(), Object::Number(2.0));
+
+    assert_eq!(child.delete("x"), Some(Object::Number(2.0)));
+    assert_eq!(child.get("x".to_string()), Some(Object::Number(1.0)));
+    // The parent's binding isn't reachable through the child's delete
+    assert_eq!(child.delete("x"), None);
+    assert!(parent.borrow().has("x"));
+}
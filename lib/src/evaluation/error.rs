@@ -0,0 +1,38 @@
+use thiserror::Error;
+
+/// Everything that can go wrong while evaluating a program
+///
+/// Each variant displays as a human-readable message, which is what the REPL shows
+#[derive(Debug, PartialEq, Error, Clone)]
+pub enum EvalError {
+    // A value had the wrong type for an operation, like `1 + 'a'`
+    #[error("{0}")]
+    TypeMismatch(String),
+    #[error("Identifier not found: {0}")]
+    UndefinedIdentifier(String),
+    #[error("Wrong number of arguments: expected {expected}, found {found}")]
+    WrongArity { expected: String, found: usize },
+    #[error("Index {index} is out of bounds for a length of {length}")]
+    IndexOutOfBounds { index: f64, length: usize },
+    #[error("{0}")]
+    NotCallable(String),
+    // Dividing or taking the remainder by zero, which would otherwise give inf or NaN
+    #[error("Division by zero")]
+    DivisionByZero,
+    // Anything else, like hitting the recursion limit
+    #[error("{0}")]
+    Runtime(String),
+}
+
+// Lets helpers that fail with a plain message be used with `?`
+impl From<String> for EvalError {
+    fn from(message: String) -> Self {
+        EvalError::Runtime(message)
+    }
+}
+
+impl From<&str> for EvalError {
+    fn from(message: &str) -> Self {
+        EvalError::Runtime(message.to_string())
+    }
+}
@@ -0,0 +1,838 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::evaluation::{
+    config::EvaluatorConfig, env::Environment, error::EvalError, object::Object, Evaluator,
+};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+pub fn eval(input: &str) -> Result<Object<'static>, EvalError> {
+    eval_with_config(input, EvaluatorConfig::default())
+}
+
+pub fn eval_with_config(
+    input: &str,
+    config: EvaluatorConfig,
+) -> Result<Object<'static>, EvalError> {
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    let program = match p.parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for err in errors {
+                println!("Parser had an error:\n{}", err);
+            }
+            panic!("Parser errored");
+        }
+    };
+    let env = Rc::new(RefCell::new(Environment::new()));
+    Evaluator::new(env, config).eval_program(program)
+}
+
+pub fn test_output(input: &str, expected: Object<'static>) {
+    match eval(input) {
+        Ok(result) => assert_eq!(result, expected),
+        Err(err) => panic!("Evaluator errored on {}:\n{}", input, err),
+    }
+}
+
+pub fn test_error(input: &str) -> String {
+    test_error_kind(input).to_string()
+}
+
+pub fn test_error_kind(input: &str) -> EvalError {
+    match eval(input) {
+        Ok(result) => panic!("Expected {} to error, got {}", input, result),
+        Err(err) => err,
+    }
+}
+
+fn hash(items: Vec<(Object<'static>, Object<'static>)>) -> Object<'static> {
+    Object::Hash(items.into_iter().collect())
+}
+
+#[test]
+fn test_hash_string_keys() {
+    test_output(
+        "{ name = 'bob', 'age' = 15 }",
+        hash(vec![
+            (
+                Object::String("name".to_string()),
+                Object::String("bob".to_string()),
+            ),
+            (Object::String("age".to_string()), Object::Number(15.0)),
+        ]),
+    );
+    test_output("{ name = 'bob' }.name", Object::String("bob".to_string()));
+}
+
+#[test]
+fn test_hash_number_key() {
+    test_output("{ 1 = 'a', 2 = 'b' }[2]", Object::String("b".to_string()));
+    test_output("{ 1 = 'a' }[3]", Object::Null);
+}
+
+#[test]
+fn test_hash_symbol_key() {
+    test_output("{ :ok = 5, :error = 10 }[:ok]", Object::Number(5.0));
+}
+
+#[test]
+fn test_unhashable_key() {
+    let err = test_error("let f = fn x -> x; { f = 1 }[f]");
+    assert!(err.contains("can't be used as a hash key"), "{}", err);
+    let err = test_error("{ a = 1 }[[1]]");
+    assert!(err.contains("can't be used as a hash key"), "{}", err);
+}
+
+#[test]
+fn test_void_and_null() {
+    test_output("let x = 1", Object::Void);
+    test_output("null", Object::Null);
+    test_output("null == null", Object::Boolean(true));
+    test_output("null == 0", Object::Boolean(false));
+}
+
+#[test]
+fn test_number_equality() {
+    test_output("0 == -0", Object::Boolean(true));
+    test_output("[0] == [-0]", Object::Boolean(true));
+    let nan = "let nan = (-1) ** 0.5;";
+    test_output(&format!("{} nan == nan", nan), Object::Boolean(false));
+    test_output(&format!("{} nan != nan", nan), Object::Boolean(true));
+    test_output(&format!("{} [nan] == [nan]", nan), Object::Boolean(false));
+    test_output(
+        &format!("{} nan :: {{ 0 -> 1, _ -> 2 }}", nan),
+        Object::Number(2.0),
+    );
+}
+
+#[test]
+fn test_integers() {
+    // `test_output` can't tell integers and floats apart, since `4 == 4.0`
+    assert!(matches!(eval("2 + 2"), Ok(Object::Integer(4))));
+    assert!(matches!(eval("5 / 2"), Ok(Object::Number(n)) if n == 2.5));
+    assert!(matches!(eval("4 / 2"), Ok(Object::Number(n)) if n == 2.0));
+    assert!(matches!(eval("2 * 3.5"), Ok(Object::Number(n)) if n == 7.0));
+    assert!(matches!(eval("7 % 3 - 2 ** 3"), Ok(Object::Integer(-7))));
+    assert!(matches!(eval("2 ** -1"), Ok(Object::Number(n)) if n == 0.5));
+    // Results that don't fit in an integer become floats
+    assert!(matches!(
+        eval("9223372036854775807 + 1"),
+        Ok(Object::Number(_))
+    ));
+    assert!(matches!(eval("2 ** 64"), Ok(Object::Number(_))));
+    assert!(matches!(eval("len('abc')"), Ok(Object::Integer(3))));
+    test_output("1 == 1.0", Object::Boolean(true));
+    test_output("2 < 2.5", Object::Boolean(true));
+    test_output("{ 1 = 'a' }[1.0]", Object::String("a".to_string()));
+    assert_eq!(eval("5 / 2").unwrap().to_string(), "2.5");
+    assert_eq!(eval("2 + 2").unwrap().to_string(), "4");
+}
+
+#[test]
+fn test_structural_equality() {
+    test_output("[[1], [2]] == [[1], [2]]", Object::Boolean(true));
+    test_output("[[1], [2]] == [[1], [3]]", Object::Boolean(false));
+    test_output(
+        "{a = [1], b = {c = 2}} == {b = {c = 2}, a = [1]}",
+        Object::Boolean(true),
+    );
+    test_output("[1] == {0 = 1}", Object::Boolean(false));
+
+    // Functions are only equal to themselves, however they're written
+    test_output("(fn x -> x) == (fn x -> x)", Object::Boolean(false));
+    test_output("let f = fn x -> x; f == f", Object::Boolean(true));
+    test_output("let f = fn x -> x; [f] == [f]", Object::Boolean(true));
+    test_output(
+        "let make = fn () -> fn x -> x; make() == make()",
+        Object::Boolean(false),
+    );
+    test_output("map == map", Object::Boolean(true));
+    test_output("map == repeat", Object::Boolean(false));
+}
+
+#[test]
+fn test_assignment_values() {
+    // `let` is a statement, so it has no value
+    test_output("let x = 5", Object::Void);
+    // Bare assignment is an expression that evaluates to the new value
+    test_output("let x = 1; x = 5", Object::Number(5.0));
+    test_output(
+        "let x = 1; let y = 2; x = y = 3; [x, y]",
+        Object::Array(vec![Object::Number(3.0), Object::Number(3.0)]),
+    );
+    test_output("let x = 1; (x = 2) + x", Object::Number(4.0));
+    test_output(
+        "let i = 0; while i < 3 { i = i + 1 }; i",
+        Object::Number(3.0),
+    );
+    // Functions update the binding they closed over
+    test_output(
+        "let count = 0; let inc = fn () -> count = count + 1; inc(); inc()",
+        Object::Number(2.0),
+    );
+
+    let err = test_error("x = 5");
+    assert!(err.contains("Can't assign to x"), "{}", err);
+}
+
+#[test]
+fn test_member_assignment() {
+    let a = || Object::String("a".to_string());
+    test_output(
+        "let h = {a = 1}; h['a'] = 2; h",
+        hash(vec![(a(), Object::Number(2.0))]),
+    );
+    // The binding gets a new hash, so the original is left alone
+    test_output(
+        "let h = {a = 1}; let g = h; h['a'] = 2; [g.a, h.a]",
+        Object::Array(vec![Object::Number(1.0), Object::Number(2.0)]),
+    );
+    test_output("let h = {}; h.b = 3; h.b += 1", Object::Number(4.0));
+    test_output(
+        "let xs = [1, 2, 3]; xs[1] = 5; xs",
+        Object::Array(vec![
+            Object::Number(1.0),
+            Object::Number(5.0),
+            Object::Number(3.0),
+        ]),
+    );
+    test_output(
+        "let h = {xs = [{a = 1}]}; h.xs[0].a = 2; h.xs[0]",
+        hash(vec![(a(), Object::Number(2.0))]),
+    );
+    // Indexes with side effects are only evaluated once
+    test_output(
+        "let i = 0; let m = [[0], [0], [0]]; m[i = i + 1][0] = 9; [m[1][0], m[2][0], i]",
+        Object::Array(vec![
+            Object::Number(9.0),
+            Object::Number(0.0),
+            Object::Number(1.0),
+        ]),
+    );
+
+    assert_eq!(
+        test_error_kind("let xs = [1]; xs[1] = 2"),
+        EvalError::IndexOutOfBounds {
+            index: 1.0,
+            length: 1
+        }
+    );
+    let err = test_error("h.a = 1");
+    assert!(err.contains("h"), "{}", err);
+}
+
+#[test]
+fn test_args() {
+    let input = "[len(args), args[1]]";
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    let program = p.parse_program().unwrap();
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut evaluator = Evaluator::new(env, EvaluatorConfig::default());
+    evaluator.set_args(vec!["--verbose".to_string(), "input.txt".to_string()]);
+    assert_eq!(
+        evaluator.eval_program(program),
+        Ok(Object::Array(vec![
+            Object::Number(2.0),
+            Object::String("input.txt".to_string())
+        ]))
+    );
+}
+
+#[test]
+fn test_while_loop() {
+    test_output(
+        "let i = 0; let total = 0
+        while i < 5 { let i = i + 1; let total = total + i };
+        [i, total]",
+        Object::Array(vec![Object::Number(5.0), Object::Number(15.0)]),
+    );
+    test_output("while false { 1 }", Object::Void);
+    // The body shares the surrounding scope, so its bindings outlive the loop
+    test_output(
+        "let i = 0; while i < 3 { let y = i; i += 1 }; y",
+        Object::Number(2.0),
+    );
+    // Returns escape the loop along with the function
+    test_output(
+        "let f = fn () -> { let i = 0; while true { let i = i + 1; if i == 3 { return i } else {} } }
+        f()",
+        Object::Number(3.0),
+    );
+}
+
+#[test]
+fn test_function_stmt() {
+    // The name is bound before the body runs, so the function can call itself
+    test_output(
+        "fn fact(n) -> if n < 2 { 1 } else { n * fact(n - 1) }
+        fact(5)",
+        Object::Number(120.0),
+    );
+    test_output("fn add(a, b = 1) -> { a + b }; add(2)", Object::Number(3.0));
+}
+
+#[test]
+fn test_break_and_continue() {
+    // Stops at the first multiple of 7, without running the rest of the body
+    test_output(
+        "let i = 0; let seen = []
+        while true { let i = i + 1; if i % 7 == 0 { break } else {}; let seen = seen + [i] };
+        [i, len(seen)]",
+        Object::Array(vec![Object::Number(7.0), Object::Number(6.0)]),
+    );
+    // Skips the odd numbers, but still checks the condition each time
+    test_output(
+        "let i = 0; let total = 0
+        while i < 10 { let i = i + 1; if i % 2 == 1 { continue } else {}; let total = total + i };
+        total",
+        Object::Number(30.0),
+    );
+    // Only the innermost loop stops
+    test_output(
+        "let i = 0; let count = 0
+        while i < 3 { let i = i + 1; let j = 0; while true { let j = j + 1; let count = count + 1; if j == 2 { break } else {} } };
+        count",
+        Object::Number(6.0),
+    );
+    let err = test_error("break");
+    assert!(
+        err.contains("break can only be used inside a loop"),
+        "{}",
+        err
+    );
+    // A loop around a call can't be controlled from inside the function
+    let err = test_error("let f = fn () -> { continue }; while true { f() }");
+    assert!(
+        err.contains("continue can only be used inside a loop"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_rest_pattern() {
+    let numbers = |items: &[f64]| Object::Array(items.iter().map(|n| Object::Number(*n)).collect());
+    test_output(
+        "let [head, ...tail] = [1, 2, 3]; [head, tail]",
+        Object::Array(vec![Object::Number(1.0), numbers(&[2.0, 3.0])]),
+    );
+    test_output("let [a, b, ...rest] = [1, 2]; rest", numbers(&[]));
+    test_output(
+        "[1, 2, 3] :: { [first, ...rest] -> rest }",
+        numbers(&[2.0, 3.0]),
+    );
+
+    // Every name needs a value, and there can't be any left over without a rest pattern
+    let err = test_error("let [a, b, c] = [1]");
+    assert!(err.contains("Can't destructure [1]"), "{}", err);
+    test_error("let [a] = [1, 2]");
+    test_error("let [a, b, ...rest] = [1]");
+    let err = test_error("let [a, [b]] = [1, 2]");
+    assert!(err.contains("Can't destructure"), "{}", err);
+    let err = test_error("let [a, b] = 5");
+    assert!(err.contains("Can't destructure 5"), "{}", err);
+}
+
+#[test]
+fn test_protect_builtins() {
+    // Shadowing is allowed by default
+    test_output("let len = 5; len", Object::Number(5.0));
+
+    let eval_protected = |input: &str| {
+        eval_with_config(
+            input,
+            EvaluatorConfig {
+                protect_builtins: true,
+                ..EvaluatorConfig::default()
+            },
+        )
+    };
+    let err = eval_protected("let len = 5").unwrap_err().to_string();
+    assert!(err.contains("Can't shadow the builtin len"), "{}", err);
+    let err = eval_protected("let [x, len] = [1, 2]")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("len"), "{}", err);
+    let err = eval_protected("let f = fn () -> { let len = 1 }; f()")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("len"), "{}", err);
+    assert_eq!(
+        eval_protected("let length = len([1]); length"),
+        Ok(Object::Number(1.0))
+    );
+}
+
+#[test]
+fn test_hash_destructuring() {
+    test_output(
+        "let person = { name = 'bob', age = 42 }
+        let { name, age } = person;
+        [name, age]",
+        Object::Array(vec![
+            Object::String("bob".to_string()),
+            Object::Number(42.0),
+        ]),
+    );
+    test_output(
+        "let { name: n, email } = { name = 'bob' }; [n, email]",
+        Object::Array(vec![Object::String("bob".to_string()), Object::Null]),
+    );
+    let err = test_error("let { name } = [1, 2]");
+    assert!(err.contains("Can't destructure"), "{}", err);
+}
+
+#[test]
+fn test_match_arm_scope() {
+    // A failed guard doesn't leave its bindings behind
+    test_output(
+        "let x = 1; let r = 5 :: { x if x > 10 -> 1, _ -> 0 }; [x, r]",
+        Object::Array(vec![Object::Number(1.0), Object::Number(0.0)]),
+    );
+    // Neither does an arm that matches, but assignments still reach outer variables
+    test_output(
+        "let x = 1; let total = 0; 5 :: { x -> { total = x } }; [x, total]",
+        Object::Array(vec![Object::Number(1.0), Object::Number(5.0)]),
+    );
+}
+
+#[test]
+fn test_nested_destructuring() {
+    test_output(
+        "let [a, _, { b, c }] = [1, 2, { b = 3, c = 4 }]; a + b + c",
+        Object::Number(8.0),
+    );
+    test_output(
+        "let [[x, ...xs], y] = [[1, 2, 3], 4]; [x, xs, y]",
+        Object::Array(vec![
+            Object::Number(1.0),
+            Object::Array(vec![Object::Number(2.0), Object::Number(3.0)]),
+            Object::Number(4.0),
+        ]),
+    );
+}
+
+#[test]
+fn test_match_guards() {
+    let classify = "let classify = fn n -> n :: {
+        x if x < 0 -> :negative,
+        0 -> :zero,
+        x if x > 100 -> :large,
+        _ -> :small
+    };";
+    for (input, expected) in &[
+        ("-5", "negative"),
+        ("0", "zero"),
+        ("500", "large"),
+        ("7", "small"),
+    ] {
+        test_output(
+            &format!("{} classify({})", classify, input),
+            Object::Symbol(expected.to_string()),
+        );
+    }
+    // Guards can use values bound by the pattern
+    test_output(
+        "[3, 1] :: { [a, b] if a < b -> :ascending, [a, b] -> :descending }",
+        Object::Symbol("descending".to_string()),
+    );
+}
+
+#[test]
+fn test_recursion_limit() {
+    let countdown = "let f = fn n -> n :: { 0 -> :done, _ -> f(n - 1) }; f(20)";
+    let config = |recursion_limit| EvaluatorConfig {
+        recursion_limit,
+        ..EvaluatorConfig::default()
+    };
+    assert_eq!(
+        eval_with_config(countdown, config(50)),
+        Ok(Object::Symbol("done".to_string()))
+    );
+    let err = eval_with_config(countdown, config(10))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("Maximum recursion depth of 10 exceeded"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_default_recursion_limit() {
+    let sum = "let sum = fn n -> if n == 0 { 0 } else { n + sum(n - 1) }";
+    test_output(&format!("{}; sum(500)", sum), Object::Number(125250.0));
+
+    // Each call nests several expressions deep, which used to overflow the stack before the limit
+    let nested = "let f = fn n -> n :: { 0 -> 0, _ -> n :: { _ -> 1 + f(n - 1) } }";
+    test_output(&format!("{}; f(900)", nested), Object::Number(900.0));
+    // Run with a small stack, since the evaluator shouldn't rely on the one it starts with
+    let program = format!("{}; f(10000)", nested);
+    let err = std::thread::Builder::new()
+        .stack_size(1024 * 1024)
+        .spawn(move || eval(&program).unwrap_err().to_string())
+        .unwrap()
+        .join()
+        .unwrap();
+    assert!(
+        err.contains("Maximum recursion depth of 1000 exceeded"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_range_patterns() {
+    let size = "let size = fn n -> n :: { 0..10 -> 'small', 10..100 -> 'big', _ -> 'other' };";
+    for (input, expected) in &[
+        ("0", "small"),
+        ("9.5", "small"),
+        ("10", "big"),
+        ("99", "big"),
+        ("100", "other"),
+        ("-1", "other"),
+        ("'5'", "other"),
+    ] {
+        test_output(
+            &format!("{} size({})", size, input),
+            Object::String(expected.to_string()),
+        );
+    }
+}
+
+#[test]
+fn test_array_pattern_length() {
+    let shape =
+        "let shape = fn xs -> xs :: { [] -> 0, [a] -> 1, [a, b] -> 2, [a, b, ...rest] -> :many };";
+    for (input, expected) in &[
+        ("[]", Object::Number(0.0)),
+        ("[1]", Object::Number(1.0)),
+        ("[1, 2]", Object::Number(2.0)),
+        ("[1, 2, 3]", Object::Symbol("many".to_string())),
+    ] {
+        test_output(&format!("{} shape({})", shape, input), expected.clone());
+    }
+    // Non-arrays just don't match array patterns
+    test_output(
+        "5 :: { [a] -> a, _ -> :none }",
+        Object::Symbol("none".to_string()),
+    );
+}
+
+#[test]
+fn test_no_matching_arm() {
+    let err = test_error("3 :: { 1 -> 'a', 2 -> 'b' }");
+    assert!(err.contains("No match arm matched value 3"), "{}", err);
+    // A failing guard doesn't count as a match either
+    let err = test_error("3 :: { x if x > 5 -> 'big' }");
+    assert!(err.contains("No match arm matched value 3"), "{}", err);
+    test_output(
+        "3 :: { 1 -> 'a', _ -> 'other' }",
+        Object::String("other".to_string()),
+    );
+}
+
+#[test]
+fn test_not_callable() {
+    let err = test_error("let x = 5; x(1)");
+    assert_eq!(
+        err,
+        "Can't call x, since it evaluated to 5, which isn't a function"
+    );
+    let err = test_error("let items = [1, 2]; items(0)");
+    assert!(err.contains("Can't call items"), "{}", err);
+    assert!(
+        err.contains("Hint: to get an item out of an array or hash"),
+        "{}",
+        err
+    );
+    let err = test_error("map([1], 5)");
+    assert!(err.contains("Expected function"), "{}", err);
+    let err = test_error("tap(1, 'nope')");
+    assert_eq!(err, "Can't call 'nope', since it isn't a function");
+}
+
+#[test]
+fn test_call_entry_points() {
+    // Calls written in the source and calls made by builtins should behave the same
+    let f = "let f = fn (x, y = 10) -> x + y;";
+    test_output(&format!("{} f(1)", f), Object::Number(11.0));
+    test_output(&format!("{} map([1], f)[0]", f), Object::Number(11.0));
+    test_output(&format!("{} tap(1, f)", f), Object::Number(1.0));
+
+    let g = "let g = fn (a, b) -> a;";
+    let direct = test_error_kind(&format!("{} g(1)", g));
+    let builtin = test_error_kind(&format!("{} map([1], g)", g));
+    assert_eq!(direct, builtin);
+    assert_eq!(
+        builtin,
+        EvalError::WrongArity {
+            expected: "2".to_string(),
+            found: 1
+        }
+    );
+}
+
+#[test]
+fn test_calls_restore_scope() {
+    // Each call used to leave the evaluator in the function's closure scope,
+    // so `y` ended up in f's scope and couldn't be seen after calling g
+    test_output(
+        "let f = fn () -> 1; let g = fn () -> 2; f(); let y = 5; g(); y",
+        Object::Number(5.0),
+    );
+    test_output(
+        "let f = fn x -> x; let a = f(1); let b = f(2); let c = map([3], f); [a, b, c[0]]",
+        Object::Array(vec![
+            Object::Number(1.0),
+            Object::Number(2.0),
+            Object::Number(3.0),
+        ]),
+    );
+}
+
+#[test]
+fn test_early_return() {
+    test_output(
+        "let f = fn x -> { return x * 2; x }; f(2) + 1",
+        Object::Number(5.0),
+    );
+    let classify = "let classify = fn x -> {
+        x :: { 0 -> { return :zero }, _ -> null }
+        if x < 0 { return :negative } else { null }
+        :positive
+    };";
+    test_output(
+        &format!("{} [classify(0), classify(-3), classify(4)]", classify),
+        Object::Array(vec![
+            Object::Symbol("zero".to_string()),
+            Object::Symbol("negative".to_string()),
+            Object::Symbol("positive".to_string()),
+        ]),
+    );
+    // Returning from an arm skips the rest of the function, including the binding
+    test_output(
+        "let f = fn x -> { let y = x :: { 1 -> { return :one }, _ -> 2 }; y + 1 }; [f(1), f(5)]",
+        Object::Array(vec![Object::Symbol("one".to_string()), Object::Number(3.0)]),
+    );
+    // The return only leaves the inner function
+    test_output(
+        "let inner = fn () -> { if true { return 1 } else { 2 }; 3 }; let outer = fn () -> inner() + 10; outer()",
+        Object::Number(11.0),
+    );
+}
+
+#[test]
+fn test_number_display() {
+    let display = |input: &str| eval(input).unwrap().to_string();
+    assert_eq!(display("3.0"), "3");
+    assert_eq!(display("0.1 + 0.2"), "0.3");
+    assert_eq!(display("1 / 3"), "0.333333333333333");
+    assert_eq!(display("-2 / 3"), "-0.666666666666667");
+    assert_eq!(display("9007199254740992 * 1000"), "9007199254740992000");
+    assert_eq!(display("0.000001 / 4"), "0.00000025");
+    assert_eq!(display("[1.5, 2]"), "[1.5, 2]");
+}
+
+#[test]
+fn test_chars() {
+    test_output("`a`", Object::Char('a'));
+    test_output("'abc'[0]", Object::Char('a'));
+    test_output("'café'[3]", Object::Char('é'));
+    test_output("'abc'[1] == `b`", Object::Boolean(true));
+    test_output("`a` + `b`", Object::String("ab".to_string()));
+    test_output("`a` + 'bc'", Object::String("abc".to_string()));
+    test_output("'ab' + `c`", Object::String("abc".to_string()));
+
+    assert_eq!(
+        test_error_kind("'abc'[3]"),
+        EvalError::IndexOutOfBounds {
+            index: 3.0,
+            length: 3
+        }
+    );
+    let err = test_error("`a` + 1");
+    assert!(err.contains("Unable to add"), "{}", err);
+}
+
+#[test]
+fn test_slices() {
+    let array = |items: &[f64]| Object::Array(items.iter().map(|n| Object::Number(*n)).collect());
+    test_output("[1, 2, 3, 4][1..3]", array(&[2.0, 3.0]));
+    test_output(
+        "let xs = [1, 2, 3, 4]; xs[0..len(xs)]",
+        array(&[1.0, 2.0, 3.0, 4.0]),
+    );
+    // Bounds past either end are clamped
+    test_output("[1, 2, 3, 4][-2..2]", array(&[1.0, 2.0]));
+    test_output("[1, 2, 3, 4][2..10]", array(&[3.0, 4.0]));
+    test_output("[1, 2, 3, 4][3..1]", array(&[]));
+    test_output("[][0..5]", array(&[]));
+
+    let err = test_error("'abc'[0..1]");
+    assert!(err.contains("Can't slice"), "{}", err);
+}
+
+#[test]
+fn test_bitwise_operators() {
+    test_output("6 & 3", Object::Number(2.0));
+    test_output("6 | 3", Object::Number(7.0));
+    test_output("6 ^ 3", Object::Number(5.0));
+    test_output("1 << 4", Object::Number(16.0));
+    test_output("-16 >> 2", Object::Number(-4.0));
+    test_output("1 | 2 == 3", Object::Boolean(true));
+
+    let err = test_error("1.5 & 1");
+    assert!(err.contains("isn't a whole number"), "{}", err);
+    let err = test_error("1 << 64");
+    assert!(err.contains("Can't shift by 64"), "{}", err);
+    let err = test_error("1 >> -1");
+    assert!(err.contains("Can't shift by -1"), "{}", err);
+    let err = test_error("'a' | 1");
+    assert!(err.contains("Can't use |"), "{}", err);
+}
+
+#[test]
+fn test_power_operator() {
+    test_output("2 ** 10", Object::Number(1024.0));
+    test_output("2 ** 3 ** 2", Object::Number(512.0));
+    test_output("4 ** 0.5", Object::Number(2.0));
+    test_output("2 ** -1", Object::Number(0.5));
+    test_output("3 * 2 ** 2", Object::Number(12.0));
+    test_output("-2 ** 2", Object::Number(-4.0));
+    test_output("(-2) ** 2", Object::Number(4.0));
+}
+
+#[test]
+fn test_default_parameters() {
+    let add = "let add = fn (x, y = 10) -> x + y;";
+    test_output(&format!("{} add(5)", add), Object::Number(15.0));
+    test_output(&format!("{} add(5, 1)", add), Object::Number(6.0));
+    let err = test_error(&format!("{} add()", add));
+    assert!(err.contains("expected 1 to 2, found 0"), "{}", err);
+    let err = test_error(&format!("{} add(1, 2, 3)", add));
+    assert!(err.contains("expected 1 to 2, found 3"), "{}", err);
+
+    // Defaults see the closure's bindings, not the caller's
+    test_output(
+        "let base = 1
+        let f = fn (x = base) -> x
+        let g = fn (base) -> f()
+        g(100)",
+        Object::Number(1.0),
+    );
+    test_output(
+        "[1, 2] |> map(_, fn (x, y = 3) -> x * y)",
+        Object::Array(vec![Object::Number(3.0), Object::Number(6.0)]),
+    );
+}
+
+#[test]
+fn test_rest_parameters() {
+    let f = "let f = fn (first, ...rest) -> rest;";
+    test_output(&format!("{} f(1)", f), Object::Array(vec![]));
+    test_output(
+        &format!("{} f(1, 2)", f),
+        Object::Array(vec![Object::Number(2.0)]),
+    );
+    test_output(
+        &format!("{} f(1, 2, 3, 4)", f),
+        Object::Array(vec![
+            Object::Number(2.0),
+            Object::Number(3.0),
+            Object::Number(4.0),
+        ]),
+    );
+    let err = test_error(&format!("{} f()", f));
+    assert!(err.contains("expected at least 1, found 0"), "{}", err);
+
+    // Defaults are filled before anything goes to the rest parameter
+    test_output(
+        "let g = fn (x, y = 10, ...zs) -> [x, y, zs]; g(1)",
+        Object::Array(vec![
+            Object::Number(1.0),
+            Object::Number(10.0),
+            Object::Array(vec![]),
+        ]),
+    );
+}
+
+#[test]
+fn test_error_kinds() {
+    assert_eq!(
+        test_error_kind("missing + 1"),
+        EvalError::UndefinedIdentifier("missing".to_string())
+    );
+    assert_eq!(
+        test_error_kind("let f = fn (a, b) -> a; f(1)"),
+        EvalError::WrongArity {
+            expected: "2".to_string(),
+            found: 1
+        }
+    );
+    assert_eq!(
+        test_error_kind("[1, 2][5]"),
+        EvalError::IndexOutOfBounds {
+            index: 5.0,
+            length: 2
+        }
+    );
+    assert!(matches!(
+        test_error_kind("1 + 'a'"),
+        EvalError::TypeMismatch(_)
+    ));
+    assert!(matches!(test_error_kind("5(1)"), EvalError::NotCallable(_)));
+    assert!(matches!(
+        test_error_kind("head(5)"),
+        EvalError::TypeMismatch(_)
+    ));
+
+    // The REPL still gets readable messages
+    assert_eq!(
+        test_error("[1, 2][-1]"),
+        "Index -1 is out of bounds for a length of 2"
+    );
+}
+
+#[test]
+fn test_division_by_zero() {
+    assert_eq!(test_error_kind("1 / 0"), EvalError::DivisionByZero);
+    assert_eq!(test_error_kind("5 % 0"), EvalError::DivisionByZero);
+    assert_eq!(test_error("let x = 0; 10 / -x"), "Division by zero");
+    test_output("0 / 5", Object::Number(0.0));
+}
+
+#[test]
+fn test_stats() {
+    let run = |input: &str, config: EvaluatorConfig| {
+        let mut p = Parser::new(Lexer::new(input), input.to_string());
+        let program = p.parse_program().unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let mut evaluator = Evaluator::new(env, config);
+        evaluator.eval_program(program).unwrap();
+        evaluator.stats()
+    };
+    let input = "let double = fn x -> x * 2
+    let total = double(1) + double(2)
+    map([1, 2, 3], double)";
+
+    assert_eq!(run(input, EvaluatorConfig::default()), None);
+    let stats = run(
+        input,
+        EvaluatorConfig {
+            collect_stats: true,
+            ..EvaluatorConfig::default()
+        },
+    )
+    .unwrap();
+    // Two direct calls, the call to map, and the three calls map makes
+    assert_eq!(stats.calls, 6);
+    // The function and the array literal
+    assert_eq!(stats.allocations, 2);
+    assert!(stats.expressions > stats.calls);
+}
@@ -1,34 +1,102 @@
 pub mod builtins;
+pub mod config;
 pub mod env;
+pub mod error;
 pub mod object;
+pub mod stats;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::mem;
 use std::rc::Rc;
 
-use crate::ast::{BlockStatement, Expr, Ident, Pattern, Program, Stmt};
+use crate::ast::{BlockStatement, Expr, Ident, Parameter, Pattern, Program, Stmt};
+use config::EvaluatorConfig;
 use env::Environment;
-use object::Object;
+use error::EvalError;
+use object::{BuiltinFunc, Object};
+use stats::EvalStats;
+
+#[cfg(test)]
+#[path = "./evaluation_test.rs"]
+mod evaluation_test;
+
+// A single expression can take about 16KB of stack in debug builds, so this leaves plenty of room
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
+pub type EvalResult<'a> = Result<Object<'a>, EvalError>;
 
-type EvalResult<'a> = Result<Object<'a>, String>;
 #[derive(Debug, Clone)]
 pub struct Evaluator<'a> {
     env: Rc<RefCell<Environment<'a>>>,
+    config: EvaluatorConfig,
+    // How many function calls deep we currently are
+    depth: usize,
+    // Shared so that evaluators cloned for builtins count into the same stats
+    stats: Option<Rc<RefCell<EvalStats>>>,
 }
 impl<'a> Evaluator<'a> {
-    pub fn new(env: Rc<RefCell<Environment<'a>>>) -> Self {
+    pub fn new(env: Rc<RefCell<Environment<'a>>>, config: EvaluatorConfig) -> Self {
         let builtins = builtins::get_builtins();
         for (name, value) in builtins {
+            let value = if config.sandboxed && builtins::IO_BUILTINS.contains(&name.as_str()) {
+                Object::Builtin(-1, builtins::disabled)
+            } else {
+                value
+            };
             let mut inner_env = env.borrow_mut();
             inner_env.set(name, value);
         }
-        Self { env }
+        let stats = if config.collect_stats {
+            Some(Rc::new(RefCell::new(EvalStats::default())))
+        } else {
+            None
+        };
+        Self {
+            env,
+            config,
+            depth: 0,
+            stats,
+        }
+    }
+
+    /// What's been counted so far, or None if `collect_stats` is off
+    pub fn stats(&self) -> Option<EvalStats> {
+        self.stats.as_ref().map(|stats| *stats.borrow())
+    }
+
+    fn record(&self, update: impl FnOnce(&mut EvalStats)) {
+        if let Some(stats) = &self.stats {
+            update(&mut stats.borrow_mut());
+        }
+    }
+
+    /// Registers a native function, callable from bliss as `name`
+    ///
+    /// An `arity` of -1 accepts any number of arguments
+    pub fn register_builtin(&mut self, name: &str, arity: isize, func: BuiltinFunc<'a>) {
+        self.env
+            .borrow_mut()
+            .set(name.to_string(), Object::Builtin(arity, func));
+    }
+
+    /// Exposes command line arguments to scripts as the global `args` array
+    pub fn set_args(&mut self, args: Vec<String>) {
+        let args = args.into_iter().map(Object::String).collect();
+        self.env
+            .borrow_mut()
+            .set("args".to_string(), Object::Array(args));
     }
     pub fn eval_program(&mut self, program: Program) -> EvalResult<'a> {
         let mut result = Object::Void;
         for stmt in program.0 {
             match self.eval_stmt(stmt)? {
                 Object::Return(value) => return Ok(*value),
+                signal @ (Object::Break | Object::Continue) => {
+                    return Err(Self::outside_loop_error(&signal))
+                }
                 value => result = value,
             }
         }
@@ -40,6 +108,8 @@ impl<'a> Evaluator<'a> {
         for stmt in stmts.0 {
             match self.eval_stmt(stmt)? {
                 Object::Return(value) => return Ok(Object::Return(value)),
+                // Stops the block, so the loop it's in can decide what happens next
+                signal @ (Object::Break | Object::Continue) => return Ok(signal),
                 value => result = value,
             }
         }
@@ -48,40 +118,193 @@ impl<'a> Evaluator<'a> {
 
     fn eval_stmt(&mut self, stmt: Stmt) -> EvalResult<'a> {
         match stmt {
-            Stmt::Expr(expr) => self.eval_expr(expr),
-            Stmt::Return(expr) => {
-                let value = self.eval_expr(expr)?;
-                Ok(Object::Return(Box::new(value)))
-            }
+            Stmt::Expr(expr) => self.eval_expr(expr.node),
+            Stmt::Return(expr) => match self.eval_expr(expr)? {
+                // `return` inside an if or match arm has already returned
+                value @ (Object::Return(_) | Object::Break | Object::Continue) => Ok(value),
+                value => Ok(Object::Return(Box::new(value))),
+            },
+            Stmt::Break => Ok(Object::Break),
+            Stmt::Continue => Ok(Object::Continue),
             Stmt::Assign(name, value) => {
+                if self.config.protect_builtins {
+                    self.check_shadowed_builtins(&name)?;
+                }
                 let value = self.eval_expr(value)?;
+                // Like `let x = y :: { ... -> { return 1 } }`, which returns before binding anything
+                if let Object::Return(_) | Object::Break | Object::Continue = value {
+                    return Ok(value);
+                }
                 match name {
                     Pattern::Ident(ident) => {
                         self.env.borrow_mut().set(ident.0, value);
                     }
-                    Pattern::Array(names) => {
-                        if let Object::Array(values) = value {
-                            names.iter().enumerate().for_each(|(index, name)| {
-                                if let Pattern::Ident(name) = name {
-                                    self.env
-                                        .borrow_mut()
-                                        .set(name.0.clone(), values[index].clone())
-                                }
-                            });
+                    pattern @ (Pattern::Array(_) | Pattern::Hash(_)) => {
+                        // Checks the length and the shape of any nested patterns, so
+                        // every name has a value to bind
+                        if !Self::eval_match_case(&pattern, &value) {
+                            return Err(EvalError::TypeMismatch(format!(
+                                "Can't destructure {} as {}",
+                                value, pattern
+                            )));
                         }
+                        Self::eval_pattern_matching(&self.env, pattern, value);
                     }
                     _ => {}
                 }
                 Ok(Object::Void)
             }
-            _ => Err("statement not supported".to_string()),
+            _ => Err(EvalError::Runtime("statement not supported".to_string())),
+        }
+    }
+
+    /// Errors if a collection of `size` items would go over `max_collection_size`
+    pub fn check_collection_size(&self, size: usize) -> Result<(), EvalError> {
+        if size > self.config.max_collection_size {
+            return Err(EvalError::Runtime(format!(
+                "Can't create a collection of {} items, the limit is {}",
+                size, self.config.max_collection_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_shadowed_builtins(&self, pattern: &Pattern) -> Result<(), EvalError> {
+        for name in pattern.bindings() {
+            if let Some(Object::Builtin(..)) = self.env.borrow().get(name.0.clone()) {
+                return Err(EvalError::Runtime(format!(
+                    "Can't shadow the builtin {} with let",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // `let` declarations are statements and evaluate to Void, but bare
+    // assignments are expressions and evaluate to the assigned value
+    fn eval_assign_expression(&mut self, name: Ident, value: Expr) -> EvalResult<'a> {
+        let value = self.eval_expr(value)?;
+        self.assign_binding(name, value.clone())?;
+        Ok(value)
+    }
+
+    fn assign_binding(&mut self, name: Ident, value: Object<'a>) -> Result<(), EvalError> {
+        if self.config.protect_builtins {
+            if let Some(Object::Builtin(..)) = self.env.borrow().get(name.0.clone()) {
+                return Err(EvalError::Runtime(format!(
+                    "Can't reassign the builtin {}",
+                    name
+                )));
+            }
+        }
+        if !self.env.borrow_mut().assign(name.0.clone(), value) {
+            return Err(EvalError::Runtime(format!(
+                "Can't assign to {}, since it hasn't been declared. Use `let {} = ...` to declare it",
+                name, name
+            )));
         }
+        Ok(())
+    }
+
+    fn eval_assign_member_expression(&mut self, target: Expr, value: Expr) -> EvalResult<'a> {
+        let value = self.eval_expr(value)?;
+        self.assign_member(target, value.clone())?;
+        Ok(value)
     }
 
+    // Replaces the member `target` refers to with `value`, copying each collection along the way
+    fn assign_member(&mut self, target: Expr, value: Object<'a>) -> Result<(), EvalError> {
+        let mut properties = vec![];
+        let mut target = target;
+        let name = loop {
+            match target {
+                Expr::Ident(name) => break name,
+                Expr::Member {
+                    property,
+                    object,
+                    computed,
+                } => {
+                    properties.push((property.node, computed));
+                    target = object.node;
+                }
+                target => return Err(EvalError::Runtime(format!("Can't assign to {}", target))),
+            }
+        };
+
+        // Each key is evaluated once, in the order it's written, so side effects only happen once
+        let mut keys = vec![];
+        for (property, computed) in properties.into_iter().rev() {
+            let key = match property {
+                Expr::Ident(ident) if !computed => Object::String(ident.0),
+                property => self.eval_expr(property)?,
+            };
+            keys.push(match key {
+                Object::Integer(n) => Object::Number(n as f64),
+                key => key,
+            });
+        }
+        // The collection each key indexes into, starting from the binding itself
+        let mut objects = vec![self.eval_expr(Expr::Ident(name.clone()))?];
+        for key in keys.iter().take(keys.len().saturating_sub(1)) {
+            let object = objects[objects.len() - 1].clone();
+            objects.push(self.eval_member_components(key.clone(), object, true)?);
+        }
+
+        let mut updated = value;
+        for (object, key) in objects.into_iter().zip(keys).rev() {
+            updated = match (object, key) {
+                (Object::Array(mut items), Object::Number(n)) => {
+                    if n < 0.0 || n as usize >= items.len() {
+                        return Err(EvalError::IndexOutOfBounds {
+                            index: n,
+                            length: items.len(),
+                        });
+                    }
+                    items[n as usize] = updated;
+                    Object::Array(items)
+                }
+                (Object::Hash(mut hash), key) if key.is_hashable() => {
+                    hash.insert(key, updated);
+                    Object::Hash(hash)
+                }
+                (Object::Hash(_), key) => {
+                    return Err(EvalError::TypeMismatch(format!(
+                        "{} can't be used as a hash key",
+                        key
+                    )))
+                }
+                (object, key) => {
+                    return Err(EvalError::TypeMismatch(format!(
+                        "Can't assign to {} of {}",
+                        key, object
+                    )))
+                }
+            };
+            self.record(|stats| stats.allocations += 1);
+        }
+        self.assign_binding(name, updated)
+    }
+
+    // Every nested expression recurses on the native stack, and how much a function call uses
+    // depends on how deeply its body nests, so the stack grows on the heap when it runs low
+    // rather than overflowing before the recursion limit is reached
     fn eval_expr(&mut self, node: Expr) -> EvalResult<'a> {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || self.eval_node(node))
+    }
+
+    fn eval_node(&mut self, node: Expr) -> EvalResult<'a> {
+        self.record(|stats| {
+            stats.expressions += 1;
+            if let Expr::String(_) | Expr::Array(_) | Expr::Hash(_) | Expr::Function { .. } = node {
+                stats.allocations += 1;
+            }
+        });
         match node {
+            Expr::Integer(value) => Ok(Object::Integer(value)),
             Expr::Number(value) => Ok(Object::Number(value)),
             Expr::String(value) => Ok(Object::String(value)),
+            Expr::Char(value) => Ok(Object::Char(value)),
             Expr::Boolean(value) => Ok(Self::native_bool_to_object(value)),
             Expr::Array(value) => {
                 let mut items = vec![];
@@ -92,51 +315,76 @@ impl<'a> Evaluator<'a> {
                 Ok(Object::Array(items))
             }
             Expr::Hash(values) => {
+                #[allow(clippy::mutable_key_type)]
                 let mut hash = HashMap::new();
                 for (key, value) in values {
+                    let key = match key {
+                        Expr::Ident(ident) => Object::String(ident.0),
+                        key => self.eval_expr(key)?,
+                    };
+                    if !key.is_hashable() {
+                        return Err(EvalError::TypeMismatch(format!(
+                            "{} can't be used as a hash key",
+                            key
+                        )));
+                    }
                     let value = self.eval_expr(value)?;
-                    hash.insert(key.0, value);
+                    hash.insert(key, value);
                 }
 
                 Ok(Object::Hash(hash))
             }
             Expr::Prefix(operator, right) => {
-                let right = self.eval_expr(*right)?;
+                let right = self.eval_expr(right.node)?;
                 self.eval_prefix_expression(&operator, right)
             }
             Expr::Infix(left, operator, right) => {
-                let left = self.eval_expr(*left)?;
-                let right = self.eval_expr(*right)?;
+                let left = self.eval_expr(left.node)?;
+                let right = self.eval_expr(right.node)?;
                 self.eval_infix_expression(left, &operator, right)
             }
             Expr::If {
                 condition,
                 consequence,
                 alternative,
-            } => self.eval_if_expression(*condition, consequence, alternative),
-            Expr::Match { condition, cases } => self.eval_match_expression(*condition, cases),
+            } => self.eval_if_expression(condition.node, consequence, alternative),
+            Expr::While { condition, body } => self.eval_while_expression(condition.node, body),
+            Expr::Match { condition, cases } => self.eval_match_expression(condition.node, cases),
+            Expr::Assign { name, value } => self.eval_assign_expression(name, value.node),
+            Expr::AssignMember { target, value } => {
+                self.eval_assign_member_expression(target.node, value.node)
+            }
             Expr::Ident(name) => match self.env.borrow().get(name.0.clone()) {
                 Some(value) => Ok(value),
                 None => {
                     // println!("{:#?}", self.env);
-                    Err(format!("Identifier not found: {}", name))
+                    Err(EvalError::UndefinedIdentifier(name.0))
                 }
             },
             Expr::Call {
                 function,
                 arguments,
-            } => self.eval_call_expression(*function, arguments),
+            } => self.eval_call_expression(function.node, arguments),
             Expr::Member {
                 property,
                 object,
                 computed,
-            } => self.eval_member_expression(*property, *object, computed),
-            Expr::Function { parameters, body } => Ok(Object::Function {
+            } => self.eval_member_expression(property.node, object.node, computed),
+            Expr::Slice { object, start, end } => {
+                self.eval_slice_expression(object.node, start.node, end.node)
+            }
+            Expr::Function {
                 parameters,
+                rest,
+                body,
+            } => Ok(Object::Function {
+                parameters,
+                rest,
                 body,
                 env: Environment::new_enclosed(&self.env).into(),
             }),
             Expr::Symbol(sym) => Ok(Object::Symbol(sym)),
+            Expr::Null => Ok(Object::Null),
         }
     }
 
@@ -145,89 +393,141 @@ impl<'a> Evaluator<'a> {
         function: Object<'a>,
         args: Vec<Object<'a>>,
     ) -> EvalResult<'a> {
-        let (params, body, env) = match function {
-            Object::Function {
-                parameters,
-                body,
-                env,
-            } => (parameters, body, env),
-            Object::Builtin(params, func) => {
-                if params < 0 || params == (args.len() as isize) {
-                    return func(args, Rc::new(RefCell::new(self.clone())));
-                }
-                return Err(format!(
-                    "Incorrect number of arguments passed: expected {}, got {}",
-                    params,
-                    args.len()
-                ));
-            }
-            _ => return Err(format!("Cannot call expression {}", function)),
-        };
-        if params.len() != args.len() {
-            return Err(format!(
-                "Wrong number of arguments: expected {}, found {}",
-                params.len(),
-                args.len()
-            ));
-        }
-
-        let mut function_env = Environment::new_enclosed(&Rc::clone(&env));
-        params
-            .iter()
-            .enumerate()
-            .for_each(|(index, param)| function_env.set(param.0.clone(), args[index].clone()));
-        self.env = Rc::new(RefCell::new(function_env));
-        let res = self.eval_block_stmt(body)?;
-
-        self.env = env;
-        Ok(res)
+        self.apply_function(function, args, None)
     }
 
     fn eval_call_expression(&mut self, function: Expr, arguments: Vec<Expr>) -> EvalResult<'a> {
+        let source = function.clone();
         let function = self.eval_expr(function)?;
         let mut args = vec![];
         for arg in arguments {
             let res = self.eval_expr(arg)?;
             args.push(res);
         }
+        self.apply_function(function, args, Some(&source))
+    }
 
-        let (params, body, env) = match function {
+    // Every call goes through here, whether it's written in the source or made by a builtin
+    // `source` is the expression that produced the function, if there is one, for error messages
+    fn apply_function(
+        &mut self,
+        function: Object<'a>,
+        args: Vec<Object<'a>>,
+        source: Option<&Expr>,
+    ) -> EvalResult<'a> {
+        self.record(|stats| stats.calls += 1);
+        let (params, rest, body, env) = match function {
             Object::Function {
                 parameters,
+                rest,
                 body,
                 env,
-            } => (parameters, body, env),
+            } => (parameters, rest, body, env),
             Object::Builtin(params, func) => {
                 if params < 0 || params == (args.len() as isize) {
                     return func(args, Rc::new(RefCell::new(self.clone())));
                 }
-                return Err(format!(
-                    "Incorrect number of arguments passed: expected {}, got {}",
-                    params,
-                    args.len()
-                ));
+                return Err(EvalError::WrongArity {
+                    expected: params.to_string(),
+                    found: args.len(),
+                });
+            }
+            _ => {
+                return Err(EvalError::NotCallable(Self::not_callable(
+                    &function, source,
+                )))
             }
-            _ => return Err(format!("Cannot call expression {}", function)),
         };
-        if params.len() != args.len() {
-            return Err(format!(
-                "Wrong number of arguments: expected {}, found {}",
-                params.len(),
-                args.len()
-            ));
+        let function_env = self.bind_parameters(&params, &rest, args, &env)?;
+        if self.depth >= self.config.recursion_limit {
+            return Err(EvalError::Runtime(format!(
+                "Maximum recursion depth of {} exceeded",
+                self.config.recursion_limit
+            )));
+        }
+        // The caller's scope has to come back afterwards, not the one the function closed over
+        let caller = mem::replace(&mut self.env, Rc::new(RefCell::new(function_env)));
+        self.depth += 1;
+        let res = self.eval_block_stmt(body);
+        self.depth -= 1;
+
+        self.env = caller;
+        // Returns from nested blocks stop at the function they're in
+        match res? {
+            Object::Return(value) => Ok(*value),
+            // A loop outside the function can't be stopped from inside it
+            signal @ (Object::Break | Object::Continue) => Err(Self::outside_loop_error(&signal)),
+            value => Ok(value),
         }
+    }
 
-        let current_env = Rc::clone(&env);
-        let mut function_env = Environment::new_enclosed(&current_env);
-        params
+    // Creates a function's environment with its arguments bound, filling in
+    // defaults for any trailing arguments that were left off and collecting
+    // extra ones into the rest parameter
+    fn bind_parameters(
+        &mut self,
+        params: &[Parameter],
+        rest: &Option<Ident>,
+        args: Vec<Object<'a>>,
+        env: &Rc<RefCell<Environment<'a>>>,
+    ) -> Result<Environment<'a>, EvalError> {
+        let required = params
             .iter()
-            .enumerate()
-            .for_each(|(index, param)| function_env.set(param.0.clone(), args[index].clone()));
-        self.env = Rc::new(RefCell::new(function_env));
-        let res = self.eval_block_stmt(body)?;
+            .filter(|(_, default)| default.is_none())
+            .count();
+        if args.len() < required || (rest.is_none() && args.len() > params.len()) {
+            let expected = if rest.is_some() {
+                format!("at least {}", required)
+            } else if required == params.len() {
+                required.to_string()
+            } else {
+                format!("{} to {}", required, params.len())
+            };
+            return Err(EvalError::WrongArity {
+                expected,
+                found: args.len(),
+            });
+        }
 
-        self.env = env;
-        Ok(res)
+        let mut function_env = Environment::new_enclosed(env);
+        let mut args = args.into_iter();
+        for (name, default) in params {
+            let value = match (args.next(), default) {
+                (Some(arg), _) => arg,
+                // Defaults are evaluated in the function's closure, not wherever it's called from
+                (None, Some(default)) => {
+                    let caller = mem::replace(&mut self.env, Rc::clone(env));
+                    let value = self.eval_expr(default.clone());
+                    self.env = caller;
+                    value?
+                }
+                (None, None) => {
+                    return Err(EvalError::Runtime(format!("Missing argument for {}", name)))
+                }
+            };
+            function_env.set(name.0.clone(), value);
+        }
+        if let Some(rest) = rest {
+            function_env.set(rest.0.clone(), Object::Array(args.collect()));
+        }
+        Ok(function_env)
+    }
+
+    // Explains why a value can't be called, including the expression it came from when we know it
+    fn not_callable(value: &Object, source: Option<&Expr>) -> String {
+        let mut message = match source {
+            Some(source) => format!(
+                "Can't call {}, since it evaluated to {}, which isn't a function",
+                source, value
+            ),
+            None => format!("Can't call {}, since it isn't a function", value),
+        };
+        if let Object::Array(_) | Object::Hash(_) = value {
+            message.push_str(
+                "\nHint: to get an item out of an array or hash, index it instead, like `items[0]`",
+            );
+        }
+        message
     }
 
     fn eval_member_expression(
@@ -248,6 +548,35 @@ impl<'a> Evaluator<'a> {
 
         self.eval_member_components(property, object, computed)
     }
+    fn eval_slice_expression(&mut self, object: Expr, start: Expr, end: Expr) -> EvalResult<'a> {
+        let object = self.eval_expr(object)?;
+        let start = self.eval_expr(start)?;
+        let end = self.eval_expr(end)?;
+        self.slice(object, start, end)
+    }
+    // Bounds are clamped to the array, so a slice never fails for being out of range
+    pub(crate) fn slice(
+        &self,
+        object: Object<'a>,
+        start: Object<'a>,
+        end: Object<'a>,
+    ) -> EvalResult<'a> {
+        match (object, start.as_number(), end.as_number()) {
+            (Object::Array(items), Some(start), Some(end)) => {
+                let clamp = |bound: f64| (bound.round().max(0.0) as usize).min(items.len());
+                let (start, end) = (clamp(start), clamp(end));
+                if start >= end {
+                    return Ok(Object::Array(vec![]));
+                }
+                self.record(|stats| stats.allocations += 1);
+                Ok(Object::Array(items[start..end].to_vec()))
+            }
+            (object, _, _) => Err(EvalError::TypeMismatch(format!(
+                "Can't slice {} from {} to {}",
+                object, start, end
+            ))),
+        }
+    }
     fn eval_member_components(
         &mut self,
         property: Object<'a>,
@@ -255,110 +584,159 @@ impl<'a> Evaluator<'a> {
         computed: bool,
     ) -> EvalResult<'a> {
         Ok(match (property, object) {
-            (Object::Number(n), Object::Array(arr)) => arr.get(n as usize).unwrap().clone(),
-            (Object::String(str), Object::Hash(hash)) => hash.get(&str).unwrap().clone(),
+            // Indexes are handled as floats, and hashes don't tell integer and float keys apart
+            (Object::Integer(n), object) => {
+                return self.eval_member_components(Object::Number(n as f64), object, computed)
+            }
+            (Object::Number(n), Object::Array(arr)) => {
+                if n < 0.0 || n as usize >= arr.len() {
+                    return Err(EvalError::IndexOutOfBounds {
+                        index: n,
+                        length: arr.len(),
+                    });
+                }
+                arr[n as usize].clone()
+            }
+            (Object::Number(n), Object::String(string)) => match string.chars().nth(n as usize) {
+                Some(ch) if n >= 0.0 => Object::Char(ch),
+                _ => {
+                    return Err(EvalError::IndexOutOfBounds {
+                        index: n,
+                        length: string.chars().count(),
+                    })
+                }
+            },
+            (key, Object::Hash(hash)) if key.is_hashable() => match hash.get(&key) {
+                Some(value) => value.clone(),
+                None => Object::Null,
+            },
             (Object::Ident(property), object) => {
                 let index = match computed {
-                    true => self.env.borrow().get(property.to_string()).unwrap(),
+                    true => match self.env.borrow().get(property.to_string()) {
+                        Some(index) => index,
+                        None => return Err(EvalError::UndefinedIdentifier(property.0)),
+                    },
                     false => Object::String(property.to_string()),
                 };
                 return self.eval_member_components(index, object, computed);
             }
-            (index, of) => return Err(format!("Incompatible types, {} and {}", index, of)),
+            (key, Object::Hash(_)) => {
+                return Err(EvalError::TypeMismatch(format!(
+                    "{} can't be used as a hash key",
+                    key
+                )))
+            }
+            (index, of) => {
+                return Err(EvalError::TypeMismatch(format!(
+                    "Incompatible types, {} and {}",
+                    index, of
+                )))
+            }
         })
     }
 
     fn eval_match_expression(
         &mut self,
         condition: Expr,
-        cases: Vec<(Pattern, BlockStatement)>,
+        cases: Vec<(Pattern, Option<Expr>, BlockStatement)>,
     ) -> EvalResult<'a> {
         let condition = self.eval_expr(condition)?;
         let current = Rc::clone(&self.env);
-        let mut result = Object::Void;
 
-        for (case, consequence) in cases {
-            let env = Rc::clone(&current);
-            let evaled = self.eval_pattern_matching(&env, case, condition.clone())?;
-            if self.eval_match_case(evaled, condition.clone()) {
-                self.env = env;
-                result = self.eval_block_stmt(consequence)?;
-                self.env = current;
-                break;
+        for (case, guard, consequence) in cases {
+            if !Self::eval_match_case(&case, &condition) {
+                continue;
+            }
+            // Each arm binds its pattern in a scope of its own, so nothing leaks out of it
+            let env = Rc::new(RefCell::new(Environment::new_enclosed(&current)));
+            Self::eval_pattern_matching(&env, case, condition.clone());
+            self.env = env;
+            // Guards see the pattern's bindings, and a falsy guard moves on to the next case
+            let guarded = match guard {
+                Some(guard) => self.eval_expr(guard).map(Self::is_truthy),
+                None => Ok(true),
+            };
+            if !matches!(guarded, Ok(true)) {
+                // The arm's scope is thrown away, along with everything it bound
+                self.env = Rc::clone(&current);
+                guarded?;
+                continue;
             }
+            let result = self.eval_block_stmt(consequence);
+            self.env = current;
+            return result;
         }
-        Ok(result)
+        // Falling through silently would hide bugs, so add a `_` case if that's intended
+        Err(EvalError::Runtime(format!(
+            "No match arm matched value {}",
+            condition
+        )))
     }
 
-    fn eval_match_case(&mut self, case: Object<'a>, condition: Object<'a>) -> bool {
-        match case {
-            Object::Array(array) => {
-                if let Object::Array(condition) = condition {
-                    for (index, element) in array.iter().enumerate() {
-                        let result =
-                            self.eval_match_case(element.clone(), condition[index].clone());
-                        if !result {
-                            return false;
-                        }
-                    }
-                    return true;
-                }
-                false
+    // Whether a value has the shape a pattern describes, without binding anything
+    fn eval_match_case(case: &Pattern, condition: &Object<'a>) -> bool {
+        match (case, condition) {
+            (Pattern::Nothing, _) | (Pattern::Ident(_), _) | (Pattern::Rest(_), _) => true,
+            (Pattern::Array(patterns), Object::Array(values)) => {
+                let fixed = match patterns.last() {
+                    Some(Pattern::Rest(_)) => &patterns[..patterns.len() - 1],
+                    _ => patterns.as_slice(),
+                };
+                let length_matches = if fixed.len() < patterns.len() {
+                    values.len() >= fixed.len()
+                } else {
+                    values.len() == fixed.len()
+                };
+                length_matches
+                    && fixed
+                        .iter()
+                        .zip(values)
+                        .all(|(pattern, value)| Self::eval_match_case(pattern, value))
             }
-            Object::Ident(_) => true,
-            _ => case == condition,
+            (Pattern::Hash(_), Object::Hash(_)) => true,
+            // Ranges include their start but not their end, like `0..5` does
+            (Pattern::Range(start, end), value) => match value.as_number() {
+                Some(value) => *start <= value && value < *end,
+                None => false,
+            },
+            (Pattern::String(str), Object::String(value)) => str == value,
+            (Pattern::Number(num), value) => value.as_number() == Some(*num),
+            (Pattern::Symbol(sym), Object::Symbol(value)) => sym == value,
+            (Pattern::Boolean(bool), Object::Boolean(value)) => bool == value,
+            _ => false,
         }
     }
 
+    // Binds the names in a pattern that's already been checked by `eval_match_case`
     fn eval_pattern_matching(
-        &mut self,
         env: &Rc<RefCell<Environment<'a>>>,
         case: Pattern,
         condition: Object<'a>,
-    ) -> EvalResult<'a> {
-        let value = match case {
-            Pattern::Nothing => Object::Ident(Ident::from("_")),
-            Pattern::Ident(ident) => {
-                env.borrow_mut().set(ident.0, condition.clone());
-                condition
-            }
-            Pattern::Array(array) => {
-                let mut arr = vec![];
-                let condition_array = match condition {
-                    Object::Array(a) => a,
-                    _ => return Err("Mismatched types".to_string()),
-                };
-                for (pattern, condition) in array.iter().zip(condition_array) {
-                    let result =
-                        self.eval_pattern_matching(env, pattern.clone(), condition.clone())?;
-                    arr.push(result);
+    ) {
+        match (case, condition) {
+            (Pattern::Ident(ident), condition) => env.borrow_mut().set(ident.0, condition),
+            (Pattern::Array(patterns), Object::Array(values)) => {
+                for (index, pattern) in patterns.into_iter().enumerate() {
+                    match pattern {
+                        Pattern::Rest(ident) => {
+                            let rest = values.iter().skip(index).cloned().collect();
+                            env.borrow_mut().set(ident.0, Object::Array(rest));
+                        }
+                        pattern => Self::eval_pattern_matching(env, pattern, values[index].clone()),
+                    }
                 }
-                Object::Array(arr)
             }
-            Pattern::Hash(hash) => {
-                let mut arr = vec![];
-                let condition_hash = match condition {
-                    Object::Hash(a) => a,
-                    _ => return Err("Mismatched types".to_string()),
-                };
-                for (key, alias) in hash {
-                    let condition = match condition_hash.get(&key.0) {
-                        Some(cond) => cond,
-                        None => &Object::Null,
-                    };
-                    let pattern = Pattern::Ident(alias.unwrap_or(key));
-                    let result = self.eval_pattern_matching(env, pattern, condition.clone())?;
-                    arr.push(result);
+            (Pattern::Hash(items), Object::Hash(hash)) => {
+                for (key, alias) in items {
+                    let value = hash
+                        .get(&Object::String(key.0.clone()))
+                        .cloned()
+                        .unwrap_or(Object::Null);
+                    env.borrow_mut().set(alias.unwrap_or(key).0, value);
                 }
-                Object::Array(arr)
             }
-            Pattern::String(str) => Object::String(str),
-            Pattern::Number(num) => Object::Number(num),
-            Pattern::Symbol(str) => Object::Symbol(str),
-            Pattern::Boolean(bool) => Object::Boolean(bool),
-        };
-
-        Ok(value)
+            _ => {}
+        }
     }
 
     fn eval_infix_expression(
@@ -369,57 +747,123 @@ impl<'a> Evaluator<'a> {
     ) -> EvalResult<'a> {
         match operator {
             "+" => self.eval_plus_operator(left, right),
-            "-" | "*" | "/" | "%" | ">" | "<" | ">=" | "<=" => {
+            "-" | "*" | "/" | "%" | "**" | ">" | "<" | ">=" | "<=" => {
                 self.eval_number_operator(left, operator, right)
             }
             "==" | "!=" => self.eval_boolean_operator(left, operator, right),
+            "&" | "|" | "^" | "<<" | ">>" => Self::eval_bitwise_operator(left, operator, right),
             ".." => self.eval_range_operator(left, right),
-            _ => Err("Unsupported operator".to_string()),
+            _ => Err(EvalError::Runtime("Unsupported operator".to_string())),
         }
     }
 
     fn eval_prefix_expression(&self, operator: &str, right: Object) -> EvalResult<'a> {
         match operator {
             "!" => Ok(self.eval_bang_operator(right)),
-            "-" => {
-                if let Object::Number(right) = right {
-                    return Ok(Object::Number(-right));
-                }
-                Err(format!("Can't negate {}", right))
-            }
-            _ => Err(format!("Couldn't evaluate operator {}", operator)),
+            "-" => match right {
+                Object::Integer(right) => Ok(right
+                    .checked_neg()
+                    .map_or(Object::Number(-(right as f64)), Object::Integer)),
+                Object::Number(right) => Ok(Object::Number(-right)),
+                right => Err(EvalError::TypeMismatch(format!("Can't negate {}", right))),
+            },
+            _ => Err(EvalError::Runtime(format!(
+                "Couldn't evaluate operator {}",
+                operator
+            ))),
         }
     }
 
     fn eval_number_operator(&self, left: Object, operator: &str, right: Object) -> EvalResult<'a> {
-        let result = if let Object::Number(left) = left {
-            if let Object::Number(right) = right {
-                match operator {
-                    "-" => Ok(Object::Number(left - right)),
-                    "*" => Ok(Object::Number(left * right)),
-                    "/" => Ok(Object::Number(left / right)),
-                    "%" => Ok(Object::Number(left % right)),
-
-                    ">" => Ok(Object::from(left > right)),
-                    "<" => Ok(Object::from(left < right)),
-                    ">=" => Ok(Object::from(left >= right)),
-                    "<=" => Ok(Object::from(left <= right)),
-                    _ => Err(format!("invalid operator {}", operator)),
-                }
-            } else {
-                Err(format!(
+        if let (Object::Integer(left), Object::Integer(right)) = (&left, &right) {
+            if let Some(result) = Self::eval_integer_operator(*left, operator, *right) {
+                return result;
+            }
+        }
+        let (left, right) = match (left.as_number(), right.as_number()) {
+            (Some(left), Some(right)) => (left, right),
+            _ => {
+                return Err(EvalError::TypeMismatch(format!(
                     "Can't use {} on {:?} and {:?}",
                     operator, left, right
-                ))
+                )))
             }
-        } else {
-            Err(format!(
-                "Can't use {} on {:?} and {:?}",
-                operator, left, right
-            ))
-        }?;
+        };
+        match operator {
+            "/" | "%" if right == 0.0 => Err(EvalError::DivisionByZero),
+            "-" => Ok(Object::Number(left - right)),
+            "*" => Ok(Object::Number(left * right)),
+            "/" => Ok(Object::Number(left / right)),
+            "%" => Ok(Object::Number(left % right)),
+            "**" => Ok(Object::Number(left.powf(right))),
+
+            ">" => Ok(Object::from(left > right)),
+            "<" => Ok(Object::from(left < right)),
+            ">=" => Ok(Object::from(left >= right)),
+            "<=" => Ok(Object::from(left <= right)),
+            _ => Err(EvalError::Runtime(format!("invalid operator {}", operator))),
+        }
+    }
 
-        Ok(result)
+    // Arithmetic on two integers, giving back None when the result has to be a float instead,
+    // like for `/`, a negative power, or a result too big for an integer
+    fn eval_integer_operator(left: i64, operator: &str, right: i64) -> Option<EvalResult<'a>> {
+        let result = match operator {
+            "%" if right == 0 => return Some(Err(EvalError::DivisionByZero)),
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "%" => left.checked_rem(right),
+            "**" => u32::try_from(right)
+                .ok()
+                .and_then(|right| left.checked_pow(right)),
+
+            ">" => return Some(Ok(Object::from(left > right))),
+            "<" => return Some(Ok(Object::from(left < right))),
+            ">=" => return Some(Ok(Object::from(left >= right))),
+            "<=" => return Some(Ok(Object::from(left <= right))),
+            _ => None,
+        };
+        result.map(|result| Ok(Object::Integer(result)))
+    }
+
+    // Bitwise operators work on whole numbers, treated as 64 bit integers
+    fn eval_bitwise_operator(left: Object, operator: &str, right: Object) -> EvalResult<'a> {
+        let whole = |value: &Object| match value {
+            Object::Integer(value) => Ok(*value),
+            Object::Number(value) if value.fract() == 0.0 && value.is_finite() => Ok(*value as i64),
+            Object::Number(_) => Err(EvalError::TypeMismatch(format!(
+                "Can't use {} on {}, since it isn't a whole number",
+                operator, value
+            ))),
+            _ => Err(EvalError::TypeMismatch(format!(
+                "Can't use {} on {} and {}",
+                operator, left, right
+            ))),
+        };
+        let (left, right) = (whole(&left)?, whole(&right)?);
+        let result = match operator {
+            "&" => left & right,
+            "|" => left | right,
+            "^" => left ^ right,
+            "<<" | ">>" => {
+                let shifted = u32::try_from(right).ok().and_then(|by| match operator {
+                    "<<" => left.checked_shl(by),
+                    _ => left.checked_shr(by),
+                });
+                match shifted {
+                    Some(shifted) => shifted,
+                    None => {
+                        return Err(EvalError::Runtime(format!(
+                            "Can't shift by {}, it has to be between 0 and 63",
+                            right
+                        )))
+                    }
+                }
+            }
+            _ => return Err(EvalError::Runtime(format!("invalid operator {}", operator))),
+        };
+        Ok(Object::Integer(result))
     }
 
     fn eval_boolean_operator(
@@ -428,55 +872,83 @@ impl<'a> Evaluator<'a> {
         operator: &str,
         right: Object<'a>,
     ) -> EvalResult<'a> {
+        // Numbers follow IEEE 754, so `0 == -0` is true and NaN never equals anything,
+        // including itself or an array holding it. Use `is_nan` to check for NaN
         match operator {
             "==" => Ok(Self::native_bool_to_object(left == right)),
             "!=" => Ok(Self::native_bool_to_object(left != right)),
-            _ => Err(format!("Invalid operator {}", operator)),
+            _ => Err(EvalError::Runtime(format!("Invalid operator {}", operator))),
         }
     }
 
     fn eval_range_operator(&self, left: Object<'a>, right: Object<'a>) -> EvalResult<'a> {
-        if let Object::Number(left) = left {
-            if let Object::Number(right) = right {
-                let left = left.round() as i64;
-                let right = right.round() as i64;
-                let mut items = vec![];
-                for item in left..right {
-                    items.push(Object::Number(item as f64));
-                }
-
-                return Ok(Object::Array(items));
+        if let (Some(start), Some(end)) = (left.as_number(), right.as_number()) {
+            let start = start.round() as i64;
+            let end = end.round() as i64;
+            self.check_collection_size(end.saturating_sub(start).max(0) as usize)?;
+            self.record(|stats| stats.allocations += 1);
+            let mut items = vec![];
+            for item in start..end {
+                items.push(Object::Integer(item));
             }
+
+            return Ok(Object::Array(items));
         }
-        Err(format!(
+        Err(EvalError::TypeMismatch(format!(
             "Can't use range operator on {} and {}",
             left, right
-        ))
+        )))
     }
 
     fn eval_plus_operator(&self, left: Object<'a>, right: Object<'a>) -> EvalResult<'a> {
         match left {
-            Object::Number(left) => {
-                if let Object::Number(right) = right {
+            Object::Integer(_) | Object::Number(_) => {
+                if let (Object::Integer(left), Object::Integer(right)) = (&left, &right) {
+                    if let Some(Ok(sum)) = Self::eval_integer_operator(*left, "+", *right) {
+                        return Ok(sum);
+                    }
+                }
+                if let (Some(left), Some(right)) = (left.as_number(), right.as_number()) {
                     return Ok(Object::Number(left + right));
                 }
-                Err(format!("Unable to add {:?} and {:?}", left, right))
+                Err(EvalError::TypeMismatch(format!(
+                    "Unable to add {:?} and {:?}",
+                    left, right
+                )))
             }
-            Object::String(left) => {
-                if let Object::String(right) = right {
+            // Chars join with strings and other chars into a string
+            Object::String(_) | Object::Char(_) => {
+                let text = |object: &Object| match object {
+                    Object::String(value) => Some(value.clone()),
+                    Object::Char(value) => Some(value.to_string()),
+                    _ => None,
+                };
+                if let (Some(left), Some(right)) = (text(&left), text(&right)) {
+                    self.record(|stats| stats.allocations += 1);
                     let new = [left, right].concat();
                     return Ok(Object::String(new));
                 }
-                Err(format!("Unable to add {:?} and {:?}", left, right))
+                Err(EvalError::TypeMismatch(format!(
+                    "Unable to add {:?} and {:?}",
+                    left, right
+                )))
             }
             Object::Array(left) => {
                 if let Object::Array(right) = right {
+                    self.check_collection_size(left.len() + right.len())?;
+                    self.record(|stats| stats.allocations += 1);
                     let new = [left, right].concat();
                     return Ok(Object::Array(new));
                 }
-                Err(format!("Unable to add {:?} and {:?}", left, right))
+                Err(EvalError::TypeMismatch(format!(
+                    "Unable to add {:?} and {:?}",
+                    left, right
+                )))
             }
-            _ => Err(format!("Unable to add {:?} and {:?}", left, right)),
+            _ => Err(EvalError::TypeMismatch(format!(
+                "Unable to add {:?} and {:?}",
+                left, right
+            ))),
         }
     }
 
@@ -498,6 +970,26 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    fn eval_while_expression(&mut self, condition: Expr, body: BlockStatement) -> EvalResult<'a> {
+        while Self::is_truthy(self.eval_expr(condition.clone())?) {
+            // Blocks share their environment, so `let` in the body carries over between iterations
+            match self.eval_block_stmt(body.clone())? {
+                Object::Return(value) => return Ok(Object::Return(value)),
+                Object::Break => break,
+                _ => {}
+            }
+        }
+        Ok(Object::Void)
+    }
+
+    fn outside_loop_error(signal: &Object) -> EvalError {
+        let keyword = match signal {
+            Object::Break => "break",
+            _ => "continue",
+        };
+        EvalError::Runtime(format!("{} can only be used inside a loop", keyword))
+    }
+
     fn native_bool_to_object(input: bool) -> Object<'a> {
         if input {
             Object::Boolean(true)
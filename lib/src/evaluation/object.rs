@@ -1,64 +1,245 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
 use std::rc::Rc;
 
-use super::{env::Environment, Evaluator};
-use crate::ast::{BlockStatement, Ident};
+use super::{env::Environment, error::EvalError, Evaluator};
+use crate::ast::{BlockStatement, Ident, Parameter};
 
 pub type BuiltinFunc<'a> =
-    fn(Vec<Object<'a>>, Rc<RefCell<Evaluator<'a>>>) -> Result<Object<'a>, String>;
+    fn(Vec<Object<'a>>, Rc<RefCell<Evaluator<'a>>>) -> Result<Object<'a>, EvalError>;
 
-#[derive(Debug, Clone, PartialEq)]
+// How many bytes Display shows before cutting off
+const BYTES_PREVIEW: usize = 8;
+// How many significant digits Display shows for numbers with a fractional part
+pub const NUMBER_PRECISION: usize = 15;
+
+#[derive(Debug, Clone)]
 pub enum Object<'a> {
+    /// A whole number, like `2`. Arithmetic between integers stays whole when it can
+    Integer(i64),
     Number(f64),
     String(String),
+    /// Raw binary data, like the contents of an image file
+    Bytes(Vec<u8>),
     Symbol(String),
+    Char(char),
     Ident(Ident),
     Boolean(bool),
     Array(Vec<Object<'a>>),
-    Hash(HashMap<String, Object<'a>>),
+    Hash(HashMap<Object<'a>, Object<'a>>),
     Return(Box<Object<'a>>),
+    /// A `break` on its way out to the loop it stops
+    Break,
+    /// A `continue` on its way out to the loop it moves on
+    Continue,
     Function {
-        parameters: Vec<Ident>,
+        parameters: Vec<Parameter>,
+        rest: Option<Ident>,
         body: BlockStatement,
         env: Rc<RefCell<Environment<'a>>>,
     },
     Builtin(isize, BuiltinFunc<'a>),
+    /// No value was produced, like the result of a `let` statement
     Void,
+    /// An explicit absence of a value, like the `null` literal or a missing hash key
     Null,
 }
 
-// impl Eq for Object {}
+/// How `==` compares values
+///
+/// Arrays and hashes compare structurally, item by item. Functions are only
+/// equal to copies of themselves: every evaluation of a function literal captures
+/// a fresh environment, so two literals never compare equal even if they're written
+/// the same way. `void` and `null` each only equal themselves, and values of
+/// different types are never equal
+impl<'a> PartialEq for Object<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Number(a), Object::Number(b)) => a == b,
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            // Integers equal floats with the same value, so `1 == 1.0`
+            (Object::Integer(a), Object::Number(b)) | (Object::Number(b), Object::Integer(a)) => {
+                *a as f64 == *b
+            }
+            (Object::String(a), Object::String(b)) | (Object::Symbol(a), Object::Symbol(b)) => {
+                a == b
+            }
+            (Object::Char(a), Object::Char(b)) => a == b,
+            (Object::Bytes(a), Object::Bytes(b)) => a == b,
+            (Object::Ident(a), Object::Ident(b)) => a == b,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::Array(a), Object::Array(b)) => a == b,
+            (Object::Hash(a), Object::Hash(b)) => a == b,
+            (Object::Return(a), Object::Return(b)) => a == b,
+            (Object::Function { env: a, .. }, Object::Function { env: b, .. }) => Rc::ptr_eq(a, b),
+            // Builtins compare by address, which is good enough for now
+            #[allow(unpredictable_function_pointer_comparisons)]
+            (Object::Builtin(a_arity, a), Object::Builtin(b_arity, b)) => {
+                a_arity == b_arity && a == b
+            }
+            (Object::Void, Object::Void)
+            | (Object::Null, Object::Null)
+            | (Object::Break, Object::Break)
+            | (Object::Continue, Object::Continue) => true,
+            _ => false,
+        }
+    }
+}
+
+// Only hashable objects (see `is_hashable`) are ever used as keys, and NaN is
+// rejected there, so equality is reflexive for every key that gets hashed
+impl<'a> Eq for Object<'a> {}
+
+impl<'a> Hash for Object<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Integers and floats can be equal, so numbers hash by their value as a float
+        if let Some(value) = self.as_number() {
+            // 0.0 == -0.0, so both need the same hash
+            let value = if value == 0.0 { 0.0f64 } else { value };
+            return value.to_bits().hash(state);
+        }
+        mem::discriminant(self).hash(state);
+        match self {
+            Object::String(value) | Object::Symbol(value) => value.hash(state),
+            Object::Boolean(value) => value.hash(state),
+            // Unhashable values never make it into a hash
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Object<'a> {
+    /// The value of an integer or a float as a float, or None for anything else
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Integer(value) => Some(*value as f64),
+            Object::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Whether this value can be used as a hash key
+    ///
+    /// Numbers, strings, symbols and booleans are hashable. NaN isn't, since it
+    /// never equals itself and could never be looked up again
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Object::Number(value) => !value.is_nan(),
+            Object::Integer(_) | Object::String(_) | Object::Symbol(_) | Object::Boolean(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A hash's entries in a stable order, so everything that walks a hash agrees
+///
+/// Keys are grouped by type (numbers, strings, symbols, then booleans) and sorted within each group
+// Keys only ever hold hashable (immutable) variants, see `Object::is_hashable`
+#[allow(clippy::mutable_key_type)]
+pub fn sorted_entries<'h, 'a>(
+    hash: &'h HashMap<Object<'a>, Object<'a>>,
+) -> Vec<(&'h Object<'a>, &'h Object<'a>)> {
+    let mut entries: Vec<_> = hash.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+    entries
+}
+
+fn compare_keys(a: &Object, b: &Object) -> Ordering {
+    fn rank(key: &Object) -> u8 {
+        match key {
+            Object::Integer(_) | Object::Number(_) => 0,
+            Object::String(_) => 1,
+            Object::Symbol(_) => 2,
+            Object::Boolean(_) => 3,
+            _ => 4,
+        }
+    }
+    match (a, b) {
+        // Keys are never NaN, see `is_hashable`
+        (Object::Integer(a), Object::Integer(b)) => a.cmp(b),
+        (Object::Integer(_) | Object::Number(_), Object::Integer(_) | Object::Number(_)) => a
+            .as_number()
+            .partial_cmp(&b.as_number())
+            .unwrap_or(Ordering::Equal),
+        (Object::String(a), Object::String(b)) | (Object::Symbol(a), Object::Symbol(b)) => a.cmp(b),
+        (Object::Boolean(a), Object::Boolean(b)) => a.cmp(b),
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Formats a number for display
+///
+/// Whole numbers never get a decimal point, and everything else is rounded to
+/// NUMBER_PRECISION significant digits, so `0.1 + 0.2` shows as `0.3`
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    // Round through scientific notation, then let f64 print the shortest form of the result
+    let rounded = format!("{:.*e}", NUMBER_PRECISION - 1, value);
+    rounded.parse::<f64>().unwrap_or(value).to_string()
+}
 
 impl<'a> fmt::Display for Object<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Object::Number(value) => write!(f, "{}", value),
+            Object::Integer(value) => write!(f, "{}", value),
+            Object::Number(value) => write!(f, "{}", format_number(*value)),
             Object::String(value) => write!(f, "'{}'", value),
+            Object::Bytes(bytes) => {
+                // Only preview the start, since binary data can be huge
+                let preview: Vec<String> = bytes
+                    .iter()
+                    .take(BYTES_PREVIEW)
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                write!(f, "<bytes {}", preview.join(" "))?;
+                if bytes.len() > BYTES_PREVIEW {
+                    write!(f, " ... ({} total)", bytes.len())?;
+                }
+                write!(f, ">")
+            }
             Object::Symbol(value) => write!(f, ":{}", value),
+            Object::Char(value) => write!(f, "`{}`", value),
             Object::Ident(value) => write!(f, "{}", value),
             Object::Boolean(value) => write!(f, "{}", value),
             Object::Array(value) => {
                 let items: Vec<String> = value.iter().map(|item| format!("{}", item)).collect();
                 write!(f, "[{}]", items.join(", "))
             }
-            Object::Return(_) => Ok(()),
+            Object::Return(_) | Object::Break | Object::Continue => Ok(()),
             Object::Void => write!(f, "<void>"),
             Object::Null => write!(f, "null"),
             Object::Hash(map) => {
-                let items: Vec<String> = map
-                    .iter()
-                    .map(|(key, value)| format!("{} = {}", key, value))
+                let items: Vec<String> = sorted_entries(map)
+                    .into_iter()
+                    .map(|(key, value)| match key {
+                        Object::String(key) => format!("{} = {}", key, value),
+                        key => format!("{} = {}", key, value),
+                    })
                     .collect();
                 write!(f, "{{{}}}", items.join(", "))
             }
             Object::Function {
                 parameters,
+                rest,
                 body,
                 env: _,
             } => {
-                let params: Vec<String> = parameters.iter().map(|param| param.clone().0).collect();
+                let mut params: Vec<String> = parameters
+                    .iter()
+                    .map(|(name, default)| match default {
+                        Some(default) => format!("{} = {}", name, default),
+                        None => name.0.clone(),
+                    })
+                    .collect();
+                if let Some(rest) = rest {
+                    params.push(format!("...{}", rest));
+                }
                 write!(f, "fn ({}) -> {{\n{}\n}}", params.join(", "), body)
             }
             Object::Builtin(_, _) => write!(f, "[builtin func]"),
@@ -0,0 +1,12 @@
+/// Counters collected while evaluating, when `EvaluatorConfig::collect_stats` is on
+///
+/// Handy for finding out which parts of a program do the most work
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalStats {
+    /// Every expression evaluated, including nested ones
+    pub expressions: usize,
+    /// Calls to functions and builtins
+    pub calls: usize,
+    /// Strings, arrays, hashes, and functions created by literals, ranges, and `+`
+    pub allocations: usize,
+}
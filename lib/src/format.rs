@@ -0,0 +1,423 @@
+use crate::ast::{BlockStatement, Expr, Parameter, Pattern, Program, Spanned, Stmt};
+use sha2::{Digest, Sha256};
+
+#[cfg(test)]
+#[path = "./format_test.rs"]
+mod format_test;
+
+const INDENT: &str = "  ";
+// Hashes longer than this are split across lines
+const MAX_INLINE_WIDTH: usize = 60;
+
+// Binding strength of each kind of expression, mirroring the parser's precedences
+// Operands that bind weaker than their surroundings get wrapped in parentheses
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+enum Precedence {
+    // Expressions that swallow everything after them, like `fn x -> x + 1`
+    Greedy,
+    Logical,
+    Equals,
+    LessGreater,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Index,
+    Range,
+    Sum,
+    Product,
+    Modulus,
+    Power,
+    Prefix,
+    Match,
+    Call,
+    Atom,
+}
+
+fn infix_precedence(operator: &str) -> Precedence {
+    match operator {
+        "&&" | "||" => Precedence::Logical,
+        "==" | "!=" => Precedence::Equals,
+        "<" | ">" | "<=" | ">=" => Precedence::LessGreater,
+        "|" => Precedence::BitOr,
+        "^" => Precedence::BitXor,
+        "&" => Precedence::BitAnd,
+        "<<" | ">>" => Precedence::Shift,
+        ".." => Precedence::Range,
+        "+" | "-" => Precedence::Sum,
+        "*" | "/" => Precedence::Product,
+        "%" => Precedence::Modulus,
+        "**" => Precedence::Power,
+        _ => Precedence::Greedy,
+    }
+}
+
+fn precedence(expr: &Expr) -> Precedence {
+    match expr {
+        Expr::Infix(_, operator, _) => infix_precedence(operator),
+        Expr::Prefix(_, _) => Precedence::Prefix,
+        Expr::Member { .. } | Expr::Slice { .. } => Precedence::Index,
+        // `a.b(c)` only binds as tightly as its weakest link
+        Expr::Call { function, .. } => match function.node {
+            Expr::Member { .. } | Expr::Call { .. } => precedence(function),
+            _ => Precedence::Call,
+        },
+        Expr::Match { .. } => Precedence::Match,
+        Expr::Assign { .. }
+        | Expr::AssignMember { .. }
+        | Expr::If { .. }
+        | Expr::While { .. }
+        | Expr::Function { .. } => Precedence::Greedy,
+        _ => Precedence::Atom,
+    }
+}
+
+/// Formats a program as canonical bliss source
+///
+/// Parsing the output produces the same program again
+pub fn format_program(program: &Program) -> String {
+    let mut out = format_stmts(program, 0);
+    out.push('\n');
+    out
+}
+
+/// A stable hash of a program, as a hex string
+///
+/// It hashes the formatted program, so it only changes when the program does,
+/// not when whitespace or grouping does. Useful as a cache key
+pub fn program_hash(program: &Program) -> String {
+    hex::encode(Sha256::digest(format_program(program).as_bytes()))
+}
+
+fn indent(depth: usize) -> String {
+    INDENT.repeat(depth)
+}
+
+fn format_stmts(block: &BlockStatement, depth: usize) -> String {
+    let stmts: Vec<String> = block
+        .0
+        .iter()
+        .map(|stmt| format_stmt(stmt, depth))
+        .collect();
+
+    let mut out = String::new();
+    for (index, stmt) in stmts.iter().enumerate() {
+        out.push_str(&indent(depth));
+        out.push_str(stmt);
+        // Newlines don't end statements, so a statement starting with something
+        // that could continue the previous one (like `-x` or `(x)`) needs a semicolon
+        if let Some(next) = stmts.get(index + 1) {
+            if next.starts_with(['-', '(', '[']) {
+                out.push(';');
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn format_stmt(stmt: &Stmt, depth: usize) -> String {
+    match stmt {
+        Stmt::Assign(pattern, value) => format!(
+            "let {} = {}",
+            format_pattern(pattern),
+            format_expr(value, depth)
+        ),
+        Stmt::Return(value) => format!("return {}", format_expr(value, depth)),
+        Stmt::Break => "break".to_string(),
+        Stmt::Continue => "continue".to_string(),
+        Stmt::Import {
+            source,
+            name,
+            alias,
+        } => {
+            let alias = match alias {
+                Some(alias) => format!(" as {}", format_pattern(alias)),
+                None => String::new(),
+            };
+            format!(
+                "import {} from {}{}",
+                format_pattern(name),
+                format_expr(source, depth),
+                alias
+            )
+        }
+        Stmt::Expr(expr) => format_expr(expr, depth),
+    }
+}
+
+fn format_block(block: &BlockStatement, depth: usize) -> String {
+    if block.0.is_empty() {
+        return "{}".to_string();
+    }
+    format!(
+        "{{\n{}\n{}}}",
+        format_stmts(block, depth + 1),
+        indent(depth)
+    )
+}
+
+// Function and match arm bodies can skip the braces when they're a single expression
+fn format_block_shorthand(block: &BlockStatement, depth: usize) -> String {
+    match block.0.as_slice() {
+        // A hash would be mistaken for a block
+        [Stmt::Expr(Spanned {
+            node: Expr::Hash(_),
+            ..
+        })] => format_block(block, depth),
+        [Stmt::Expr(expr)] => format_expr(expr, depth),
+        _ => format_block(block, depth),
+    }
+}
+
+fn format_operand(expr: &Expr, depth: usize, parens: bool) -> String {
+    let formatted = format_expr(expr, depth);
+    if parens {
+        format!("({})", formatted)
+    } else {
+        formatted
+    }
+}
+
+// Members and calls can only follow other members, calls, or atoms without parentheses
+fn format_postfix_operand(expr: &Expr, depth: usize) -> String {
+    let parens = !matches!(
+        expr,
+        Expr::Member { .. } | Expr::Slice { .. } | Expr::Call { .. } | Expr::Ident(_)
+    ) && precedence(expr) != Precedence::Atom;
+    format_operand(expr, depth, parens)
+}
+
+fn format_string(value: &str) -> String {
+    // There aren't any escapes, so pick whichever quote isn't used
+    if value.contains('\'') && !value.contains('"') {
+        format!("\"{}\"", value)
+    } else {
+        format!("'{}'", value)
+    }
+}
+
+fn format_list(items: Vec<String>) -> String {
+    items.join(", ")
+}
+
+fn format_expr(expr: &Expr, depth: usize) -> String {
+    match expr {
+        Expr::Integer(value) => value.to_string(),
+        // Whole floats keep their decimal point, so they don't turn into integers
+        Expr::Number(value) if value.fract() == 0.0 => format!("{:.1}", value),
+        Expr::Number(value) => value.to_string(),
+        Expr::Ident(ident) => ident.to_string(),
+        Expr::Boolean(value) => value.to_string(),
+        Expr::String(value) => format_string(value),
+        Expr::Symbol(value) => format!(":{}", value),
+        Expr::Char(value) => format!("`{}`", value),
+        Expr::Null => "null".to_string(),
+        Expr::Assign { name, value } => format!("{} = {}", name, format_expr(value, depth)),
+        Expr::AssignMember { target, value } => format!(
+            "{} = {}",
+            format_expr(target, depth),
+            format_expr(value, depth)
+        ),
+        Expr::Prefix(operator, right) => {
+            // Powers bind tighter than negation, so `-a ** 2` needs no parens
+            let operand = match operator.as_str() {
+                "-" => Precedence::Power,
+                _ => Precedence::Prefix,
+            };
+            format!(
+                "{}{}",
+                operator,
+                format_operand(right, depth, precedence(right) < operand)
+            )
+        }
+        Expr::Infix(left, operator, right) => {
+            let own = infix_precedence(operator);
+            // Everything but `**` is left associative, so equal precedence on the
+            // other side needs parens
+            let (left_parens, right_parens) = if own == Precedence::Power {
+                let negated = matches!(&left.node, Expr::Prefix(operator, _) if operator == "-");
+                (precedence(left) <= own || negated, precedence(right) < own)
+            } else {
+                (precedence(left) < own, precedence(right) <= own)
+            };
+            format!(
+                "{} {} {}",
+                format_operand(left, depth, left_parens),
+                operator,
+                format_operand(right, depth, right_parens)
+            )
+        }
+        Expr::Member {
+            property,
+            object,
+            computed,
+        } => {
+            let object = format_postfix_operand(object, depth);
+            if *computed {
+                format!("{}[{}]", object, format_expr(property, depth))
+            } else {
+                format!("{}.{}", object, format_expr(property, depth))
+            }
+        }
+        // The bounds are formatted like the operands of `..`
+        Expr::Slice { object, start, end } => format!(
+            "{}[{}..{}]",
+            format_postfix_operand(object, depth),
+            format_operand(start, depth, precedence(start) < Precedence::Range),
+            format_operand(end, depth, precedence(end) <= Precedence::Range)
+        ),
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => format!(
+            "if {} {} else {}",
+            format_expr(condition, depth),
+            format_block(consequence, depth),
+            format_block(alternative, depth)
+        ),
+        Expr::While { condition, body } => format!(
+            "while {} {}",
+            format_expr(condition, depth),
+            format_block(body, depth)
+        ),
+        Expr::Function {
+            parameters,
+            rest,
+            body,
+        } => {
+            let mut formatted: Vec<String> = parameters
+                .iter()
+                .map(|parameter| format_parameter(parameter, depth))
+                .collect();
+            if let Some(rest) = rest {
+                formatted.push(format!("...{}", rest));
+            }
+            let parameters = match (parameters.as_slice(), rest) {
+                ([(parameter, None)], None) => parameter.to_string(),
+                _ => format!("({})", format_list(formatted)),
+            };
+            format!(
+                "fn {} -> {}",
+                parameters,
+                format_block_shorthand(body, depth)
+            )
+        }
+        Expr::Call {
+            function,
+            arguments,
+        } => format!(
+            "{}({})",
+            format_postfix_operand(function, depth),
+            format_list(
+                arguments
+                    .iter()
+                    .map(|argument| format_expr(argument, depth))
+                    .collect()
+            )
+        ),
+        Expr::Match { condition, cases } => {
+            let condition =
+                format_operand(condition, depth, precedence(condition) < Precedence::Match);
+            if cases.is_empty() {
+                return format!("{} :: {{}}", condition);
+            }
+            let cases: Vec<String> = cases
+                .iter()
+                .map(|(pattern, guard, body)| {
+                    let guard = match guard {
+                        Some(guard) => format!(" if {}", format_expr(guard, depth + 1)),
+                        None => String::new(),
+                    };
+                    format!(
+                        "{}{}{} -> {}",
+                        indent(depth + 1),
+                        format_pattern(pattern),
+                        guard,
+                        format_block_shorthand(body, depth + 1)
+                    )
+                })
+                .collect();
+            format!(
+                "{} :: {{\n{}\n{}}}",
+                condition,
+                cases.join(",\n"),
+                indent(depth)
+            )
+        }
+        Expr::Array(items) => format!(
+            "[{}]",
+            format_list(items.iter().map(|item| format_expr(item, depth)).collect())
+        ),
+        Expr::Hash(items) => format_hash(items, depth),
+    }
+}
+
+fn format_parameter((name, default): &Parameter, depth: usize) -> String {
+    match default {
+        Some(default) => format!("{} = {}", name, format_expr(default, depth)),
+        None => name.to_string(),
+    }
+}
+
+fn format_hash(items: &[(Expr, Expr)], depth: usize) -> String {
+    if items.is_empty() {
+        return "{}".to_string();
+    }
+    let entries = |depth: usize| -> Vec<String> {
+        items
+            .iter()
+            .map(|(key, value)| match (key, value) {
+                // Shorthand for `{ foo = foo }`
+                (Expr::Ident(key), Expr::Ident(value)) if key == value => key.to_string(),
+                (key, value) => format!(
+                    "{} = {}",
+                    format_expr(key, depth),
+                    format_expr(value, depth)
+                ),
+            })
+            .collect()
+    };
+
+    let inline = format!("{{ {} }}", format_list(entries(depth)));
+    if inline.len() <= MAX_INLINE_WIDTH && !inline.contains('\n') {
+        return inline;
+    }
+
+    let entries: Vec<String> = entries(depth + 1)
+        .iter()
+        .map(|entry| format!("{}{}", indent(depth + 1), entry))
+        .collect();
+    format!("{{\n{}\n{}}}", entries.join(",\n"), indent(depth))
+}
+
+fn format_pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::String(value) => format_string(value),
+        Pattern::Number(value) => value.to_string(),
+        Pattern::Boolean(value) => value.to_string(),
+        Pattern::Symbol(value) => format!(":{}", value),
+        Pattern::Ident(ident) => ident.to_string(),
+        Pattern::Nothing => "_".to_string(),
+        Pattern::Rest(ident) => format!("...{}", ident),
+        Pattern::Range(start, end) => format!("{}..{}", start, end),
+        Pattern::Array(items) => format!(
+            "[{}]",
+            format_list(items.iter().map(format_pattern).collect())
+        ),
+        Pattern::Hash(items) if items.is_empty() => "{}".to_string(),
+        Pattern::Hash(items) => format!(
+            "{{ {} }}",
+            format_list(
+                items
+                    .iter()
+                    .map(|(key, alias)| match alias {
+                        Some(alias) => format!("{}: {}", key, alias),
+                        None => key.to_string(),
+                    })
+                    .collect()
+            )
+        ),
+    }
+}
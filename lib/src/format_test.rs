@@ -0,0 +1,161 @@
+use super::*;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+fn parse(input: &str) -> Program {
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    match p.parse_program() {
+        Ok(program) => program,
+        Err(errors) => panic!("failed to parse {:?}: {:?}", input, errors),
+    }
+}
+
+// Formats `input`, checks that the output parses back to the same program,
+// and that formatting again doesn't change anything
+fn round_trip(input: &str) -> String {
+    let program = parse(input);
+    let formatted = format_program(&program);
+    assert_eq!(parse(&formatted), program, "formatted as:\n{}", formatted);
+    assert_eq!(format_program(&parse(&formatted)), formatted);
+    formatted
+}
+
+#[test]
+fn test_function_with_match() {
+    let input = "
+let fib = fn (n, a, b) -> n :: {
+  0 -> a, 1 -> b,
+  _ -> { let next = a + b
+  fib(n - 1, b, next) }
+}
+log(fib(10, 0, 1))";
+    let expected = "let fib = fn (n, a, b) -> n :: {
+  0 -> a,
+  1 -> b,
+  _ -> {
+    let next = a + b
+    fib(n - 1, b, next)
+  }
+}
+log(fib(10, 0, 1))
+";
+    assert_eq!(round_trip(input), expected);
+}
+
+#[test]
+fn test_blocks() {
+    let input = "if x > 1 { log('big'); x } else {}
+let f = fn () -> { { a = 1 } }
+let g = fn x -> { let y = x; y }";
+    let expected = "if x > 1 {
+  log('big')
+  x
+} else {}
+let f = fn () -> {
+  { a = 1 }
+}
+let g = fn x -> {
+  let y = x
+  y
+}
+";
+    assert_eq!(round_trip(input), expected);
+}
+
+#[test]
+fn test_hashes() {
+    let short = round_trip("let h = {a = 1, 'b' = 2, :c = 3, foo}");
+    assert_eq!(short, "let h = { a = 1, 'b' = 2, :c = 3, foo }\n");
+
+    let long = round_trip(
+        "let config = {name = 'bliss', version = '0.1.0', authors = ['someone', 'someone else']}",
+    );
+    assert_eq!(
+        long,
+        "let config = {
+  name = 'bliss',
+  version = '0.1.0',
+  authors = ['someone', 'someone else']
+}
+"
+    );
+}
+
+#[test]
+fn test_minimal_parentheses() {
+    assert_eq!(round_trip("(1 + 2) * 3 - 4"), "(1 + 2) * 3 - 4\n");
+    assert_eq!(round_trip("1 - (2 - 3)"), "1 - (2 - 3)\n");
+    assert_eq!(round_trip("(a | b) & (c << 1)"), "(a | b) & c << 1\n");
+    assert_eq!(round_trip("(a ** b) ** (c ** d)"), "(a ** b) ** c ** d\n");
+    assert_eq!(round_trip("-(a + b)"), "-(a + b)\n");
+    assert_eq!(round_trip("-(a ** 2)"), "-a ** 2\n");
+    assert_eq!(round_trip("(-a) ** 2"), "(-a) ** 2\n");
+    assert_eq!(round_trip("a.b(1)[2]"), "a.b(1)[2]\n");
+    assert_eq!(round_trip("(fn x -> x)(1)"), "(fn x -> x)(1)\n");
+}
+
+#[test]
+fn test_preserves_grouping() {
+    // Indexing binds looser than arithmetic, so these need to keep their parentheses
+    round_trip("a + (b[0])");
+    round_trip("x + (a.b(1))");
+    round_trip("-(a.b)");
+    round_trip("(1..5)[2]");
+    round_trip("a[(1..2)..3]");
+    round_trip("(a.b) :: { _ -> 1 }");
+    round_trip("1 + (x :: { 1 -> 2, _ -> 3 }) * 2");
+}
+
+#[test]
+fn test_separates_statements() {
+    // Without a semicolon, `-1` would be parsed as a subtraction
+    assert_eq!(
+        round_trip("let x = 1; -1; x; [x]"),
+        "let x = 1;\n-1\nx;\n[x]\n"
+    );
+    assert_eq!(round_trip("f; (fn x -> x)(1)"), "f;\n(fn x -> x)(1)\n");
+}
+
+#[test]
+fn test_misc() {
+    round_trip("import foo from 'foo.bliss'");
+    round_trip("import foo from 'foo.bliss' as bar");
+    round_trip("return null");
+    assert_eq!(
+        round_trip("xs[1 + 1..len(xs)][0]"),
+        "xs[1 + 1..len(xs)][0]\n"
+    );
+    round_trip("let f = fn (x, y = 10, z = [1, 2]) -> x + y");
+    round_trip("let f = fn (x = 1) -> x");
+    assert_eq!(round_trip("fn f(x) -> x"), "let f = fn x -> x\n");
+    round_trip("let f = fn (...xs) -> xs");
+    round_trip("let f = fn (x, y = 2, ...zs) -> zs");
+    round_trip("while i < 3 { let i = i + 1 }");
+    round_trip("while true { if done { break } else { continue } }");
+    assert_eq!(
+        round_trip("x = y = 1; (x = 2) + 1"),
+        "x = y = 1;\n(x = 2) + 1\n"
+    );
+    round_trip("h.a = 1; h['b'][0] += 2");
+    round_trip("x :: { n if n > 0 && n < 10 -> n, _ -> 0 }");
+    round_trip("x :: { 0..10 -> :small, 10..100.5 -> :big }");
+    round_trip("let [a, [b, _]] = [1, [2, 3]]");
+    round_trip("let [head, ...tail] = xs");
+    round_trip("let { a, b } = h");
+    round_trip("let { a: x, b } = h");
+    round_trip("x :: { 'str' -> 1, :sym -> 2, true -> 3, 4 -> 4, [a, b] -> a }");
+    round_trip("\"it's\"");
+    round_trip("`a` + 'b'");
+    assert_eq!(round_trip("2.0 + 2"), "2.0 + 2\n");
+    round_trip("[1, 2, 3] |> map(_, fn x -> x * 2) |> log");
+}
+
+#[test]
+fn test_program_hash() {
+    let hash = program_hash(&parse("let x = 1 + 2; log(x)"));
+    assert_eq!(hash.len(), 64);
+    // Layout doesn't matter, only what the program does
+    assert_eq!(program_hash(&parse("let x = (1 + 2)\n\nlog( x )")), hash);
+    assert_ne!(program_hash(&parse("let x = 1 + 3; log(x)")), hash);
+    assert_ne!(program_hash(&parse("let x = 1 + 2")), hash);
+}
@@ -1,5 +1,5 @@
 use super::*;
-use crate::token::TokenType;
+use crate::token::{Token, TokenType};
 // Currently preferring many smaller tests over one giant one for a couple of reasons
 // - More readable
 // - Each test has an actual purpose
@@ -38,14 +38,107 @@ fn test_two_chars() {
         TokenType::LessEq,
         TokenType::Ident(String::from("e")),
         TokenType::Match,
-        TokenType::Number(0.0),
+        TokenType::Integer(0),
         TokenType::Range,
-        TokenType::Number(5.0),
+        TokenType::Integer(5),
         TokenType::Arrow,
     ];
     test_tokens(input, tests);
 }
 
+#[test]
+fn test_compound_assign() {
+    let input = "a += b -= c *= d /= e %= f - g";
+    let tests = vec![
+        TokenType::Ident(String::from("a")),
+        TokenType::PlusAssign,
+        TokenType::Ident(String::from("b")),
+        TokenType::MinusAssign,
+        TokenType::Ident(String::from("c")),
+        TokenType::AsteriskAssign,
+        TokenType::Ident(String::from("d")),
+        TokenType::SlashAssign,
+        TokenType::Ident(String::from("e")),
+        TokenType::ModulusAssign,
+        TokenType::Ident(String::from("f")),
+        TokenType::Minus,
+        TokenType::Ident(String::from("g")),
+    ];
+    test_tokens(input, tests);
+}
+
+#[test]
+fn test_spread() {
+    let input = "[a, ...b] 0..5";
+    let tests = vec![
+        TokenType::LeftBracket,
+        TokenType::Ident(String::from("a")),
+        TokenType::Comma,
+        TokenType::Spread,
+        TokenType::Ident(String::from("b")),
+        TokenType::RightBracket,
+        TokenType::Integer(0),
+        TokenType::Range,
+        TokenType::Integer(5),
+    ];
+    test_tokens(input, tests);
+}
+
+#[test]
+fn test_pipe() {
+    let input = "a |> b || c";
+    let tests = vec![
+        TokenType::Ident(String::from("a")),
+        TokenType::Pipe,
+        TokenType::Ident(String::from("b")),
+        TokenType::Or,
+        TokenType::Ident(String::from("c")),
+    ];
+    test_tokens(input, tests);
+}
+
+#[test]
+fn test_bitwise() {
+    let input = "a & b | c ^ d << e >> f && g || h |> i <= j";
+    let tests = vec![
+        TokenType::Ident(String::from("a")),
+        TokenType::BitAnd,
+        TokenType::Ident(String::from("b")),
+        TokenType::BitOr,
+        TokenType::Ident(String::from("c")),
+        TokenType::BitXor,
+        TokenType::Ident(String::from("d")),
+        TokenType::ShiftLeft,
+        TokenType::Ident(String::from("e")),
+        TokenType::ShiftRight,
+        TokenType::Ident(String::from("f")),
+        TokenType::And,
+        TokenType::Ident(String::from("g")),
+        TokenType::Or,
+        TokenType::Ident(String::from("h")),
+        TokenType::Pipe,
+        TokenType::Ident(String::from("i")),
+        TokenType::LessEq,
+        TokenType::Ident(String::from("j")),
+    ];
+    test_tokens(input, tests);
+}
+
+#[test]
+fn test_power() {
+    let input = "2 ** 3 * 4 *= 5";
+    let tests = vec![
+        TokenType::Integer(2),
+        TokenType::Power,
+        TokenType::Integer(3),
+        TokenType::Asterisk,
+        TokenType::Integer(4),
+        TokenType::AsteriskAssign,
+        TokenType::Integer(5),
+    ];
+    test_tokens(input, tests);
+}
+
 #[test]
 fn test_identifiers() {
     let input = "abc my_number5 foo bar foobar";
@@ -70,6 +163,69 @@ fn test_strings() {
     test_tokens(input, tests);
 }
 
+#[test]
+fn test_chars() {
+    let input = "`a` + `é` `` `ab`";
+    let tests = vec![
+        TokenType::Char('a'),
+        TokenType::Plus,
+        TokenType::Char('é'),
+        TokenType::Illegal,
+    ];
+    test_tokens(input, tests);
+}
+
+#[test]
+fn test_token_positions() {
+    // Positions are character offsets, starting at the token and ending just after it
+    let mut l = Lexer::new("let x = 10");
+    let expected = vec![
+        Token {
+            tok: TokenType::Let,
+            position: 0..3,
+        },
+        Token {
+            tok: TokenType::Ident(String::from("x")),
+            position: 4..5,
+        },
+        Token {
+            tok: TokenType::Assign,
+            position: 6..7,
+        },
+        Token {
+            tok: TokenType::Integer(10),
+            position: 8..10,
+        },
+    ];
+    assert_eq!(l.tokenize(), expected);
+}
+
+#[test]
+fn test_eof_position() {
+    let input = "let x = 1";
+    let mut l = Lexer::new(input);
+    let tokens = l.tokenize();
+    assert_eq!(tokens.len(), 4);
+    let eof = l.next_token();
+    assert_eq!(eof.tok, TokenType::Eof);
+    assert_eq!(eof.position, input.len()..input.len());
+    // Eof keeps coming back once the input runs out
+    assert_eq!(l.next_token(), eof);
+}
+
+#[test]
+fn test_unterminated_string() {
+    test_tokens(
+        "x + 'abc",
+        vec![
+            TokenType::Ident(String::from("x")),
+            TokenType::Plus,
+            TokenType::Illegal,
+            TokenType::Eof,
+        ],
+    );
+}
+
 #[test]
 fn test_keywords() {
     let input = "import stuff from 'the place'";
@@ -82,15 +238,34 @@ fn test_keywords() {
     test_tokens(input, tests);
 }
 
+#[test]
+fn test_null() {
+    let input = "null nullable";
+    let tests = vec![TokenType::Null, TokenType::Ident(String::from("nullable"))];
+    test_tokens(input, tests);
+}
+
 #[test]
 fn test_numbers() {
     let input = "5 + 4.5 * 8000";
     let tests = vec![
-        TokenType::Number(5.0),
+        TokenType::Integer(5),
         TokenType::Plus,
         TokenType::Number(4.5),
         TokenType::Asterisk,
-        TokenType::Number(8000.0),
+        TokenType::Integer(8000),
+    ];
+    test_tokens(input, tests);
+}
+
+#[test]
+fn test_integer_literals() {
+    // Literals too big for an integer are read as floats instead
+    let input = "2.0 9223372036854775807 9223372036854775808";
+    let tests = vec![
+        TokenType::Number(2.0),
+        TokenType::Integer(i64::MAX),
+        TokenType::Number(9223372036854775808.0),
     ];
     test_tokens(input, tests);
 }
@@ -99,9 +274,9 @@ fn test_numbers() {
 fn test_float_lookahead() {
     let input = "1..3";
     let tests = vec![
-        TokenType::Number(1.0),
+        TokenType::Integer(1),
         TokenType::Range,
-        TokenType::Number(3.0),
+        TokenType::Integer(3),
     ];
     test_tokens(input, tests)
 }
@@ -127,3 +302,56 @@ fn test_single() {
     ];
     test_tokens(input, tests);
 }
+
+// Applies an edit to `before`, checking that relexing matches lexing from scratch
+fn test_relex(
+    before: &str,
+    start: usize,
+    old_end: usize,
+    inserted: &str,
+) -> (Vec<Token>, Vec<Token>) {
+    let mut after: Vec<char> = before.chars().collect();
+    after.splice(start..old_end, inserted.chars());
+    let after: String = after.into_iter().collect();
+    let edit = Edit {
+        start,
+        old_end,
+        new_end: start + inserted.chars().count(),
+    };
+
+    let old = Lexer::new(before).tokenize();
+    let new = relex(&after, &old, &edit);
+    assert_eq!(new, Lexer::new(&after).tokenize(), "relexing {:?}", after);
+    (old, new)
+}
+
+#[test]
+fn test_relex_only_changes_edited_tokens() {
+    let (old, new) = test_relex("let x = 1 + 2; let y = x", 8, 9, "10");
+    assert_eq!(old.len(), new.len());
+    let changed: Vec<usize> = (0..old.len())
+        .filter(|&i| old[i].tok != new[i].tok)
+        .collect();
+    assert_eq!(changed, vec![3]);
+    assert_eq!(new[3].tok, TokenType::Integer(10));
+    // Tokens before the edit keep their positions, and the ones after move over
+    assert_eq!(new[2], old[2]);
+    assert_eq!(
+        new[4].position,
+        old[4].position.start + 1..old[4].position.end + 1
+    );
+}
+
+#[test]
+fn test_relex_merges_tokens() {
+    // Edits can join or split the tokens around them
+    test_relex("a b", 1, 2, "");
+    test_relex("ab", 1, 1, " ");
+    test_relex("fn x - x", 6, 6, ">");
+    test_relex("1 . 5", 1, 2, "");
+    test_relex("x = 'abc' + y", 5, 7, "");
+    // Deleting a large region, or everything
+    test_relex("let a = 1; let b = 2; let c = 3", 4, 25, "");
+    test_relex("let a = 1", 0, 9, "");
+    test_relex("", 0, 0, "let a = 1");
+}
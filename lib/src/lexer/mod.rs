@@ -10,20 +10,57 @@ mod lexer_test;
 
 type IsFunc = dyn Fn(char) -> bool;
 
-// TODO: Add positions
+// How many characters past the end of a token the lexer may look to decide where it ends
+const LOOKAHEAD: usize = 2;
+
+/// A change to the source, in character offsets
+/// The text in `start..old_end` was replaced by the text now in `start..new_end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+#[derive(Clone)]
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
     offset: usize,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &str) -> Lexer {
+    pub fn new(input: &str) -> Lexer<'_> {
         Lexer {
             input: input.chars().peekable(),
             offset: 0,
         }
     }
 
+    /// Creates a lexer that starts `offset` characters into `input`
+    /// Token positions are still relative to the start of `input`
+    pub fn starting_at(input: &str, offset: usize) -> Lexer<'_> {
+        let mut chars = input.chars().peekable();
+        for _ in 0..offset {
+            chars.next();
+        }
+        Lexer {
+            input: chars,
+            offset,
+        }
+    }
+
+    /// Lexes the rest of the input, leaving out the final Eof
+    pub fn tokenize(&mut self) -> Vec<Token> {
+        let mut tokens = vec![];
+        loop {
+            let token = self.next_token();
+            if token.tok == TokenType::Eof {
+                return tokens;
+            }
+            tokens.push(token);
+        }
+    }
+
     fn peek(&mut self) -> Option<&char> {
         self.input.peek()
     }
@@ -38,8 +75,12 @@ impl<'a> Lexer<'a> {
     }
 
     fn read(&mut self) -> Option<char> {
-        self.offset += 1;
-        self.input.next()
+        let ch = self.input.next();
+        // Running out of input doesn't move past the end
+        if ch.is_some() {
+            self.offset += 1;
+        }
+        ch
     }
 
     fn peek_is(&mut self, expected: char) -> bool {
@@ -65,25 +106,48 @@ impl<'a> Lexer<'a> {
         match ch {
             // Symbols and Operators
             '=' => self.two_char('=', TokenType::Assign, TokenType::Eq),
-            '+' => TokenType::Plus,
-            '-' => self.two_char('>', TokenType::Minus, TokenType::Arrow),
-            '*' => TokenType::Asterisk,
-            '%' => TokenType::Modulus,
-            '/' => TokenType::Slash,
+            '+' => self.two_char('=', TokenType::Plus, TokenType::PlusAssign),
+            '-' => match self.peek() {
+                Some('=') => self.two_char('=', TokenType::Minus, TokenType::MinusAssign),
+                _ => self.two_char('>', TokenType::Minus, TokenType::Arrow),
+            },
+            '*' => match self.peek() {
+                Some('=') => self.two_char('=', TokenType::Asterisk, TokenType::AsteriskAssign),
+                _ => self.two_char('*', TokenType::Asterisk, TokenType::Power),
+            },
+            '%' => self.two_char('=', TokenType::Modulus, TokenType::ModulusAssign),
+            '/' => self.two_char('=', TokenType::Slash, TokenType::SlashAssign),
             /*
             There are some complications with leading zero support though
             - It's potentially ambiguous (example ident.5)
             - We can't take things like this into context until parsing, so it might be better to handle this there (especially since that's also where we convert numbers to actual numbers)
              */
-            '.' => self.two_char('.', TokenType::Period, TokenType::Range),
+            '.' => match self.multipeek(2) {
+                Some('.') if self.peek_is('.') => {
+                    self.read();
+                    self.read();
+                    TokenType::Spread
+                }
+                _ => self.two_char('.', TokenType::Period, TokenType::Range),
+            },
             // Equality Operators
-            '>' => self.two_char('=', TokenType::Greater, TokenType::GreaterEq),
-            '<' => self.two_char('=', TokenType::Less, TokenType::LessEq),
+            '>' => match self.peek() {
+                Some('>') => self.two_char('>', TokenType::Greater, TokenType::ShiftRight),
+                _ => self.two_char('=', TokenType::Greater, TokenType::GreaterEq),
+            },
+            '<' => match self.peek() {
+                Some('<') => self.two_char('<', TokenType::Less, TokenType::ShiftLeft),
+                _ => self.two_char('=', TokenType::Less, TokenType::LessEq),
+            },
             '!' => self.two_char('=', TokenType::Bang, TokenType::NotEq),
 
             // Logical operators
-            '|' => self.two_char('|', TokenType::Illegal, TokenType::Or),
-            '&' => self.two_char('&', TokenType::Illegal, TokenType::And),
+            '|' => match self.peek() {
+                Some('|') => self.two_char('|', TokenType::BitOr, TokenType::Or),
+                _ => self.two_char('>', TokenType::BitOr, TokenType::Pipe),
+            },
+            '&' => self.two_char('&', TokenType::BitAnd, TokenType::And),
+            '^' => TokenType::BitXor,
 
             // Delimiters
             ',' => TokenType::Comma,
@@ -100,10 +164,16 @@ impl<'a> Lexer<'a> {
             }
 
             // Strings
-            '\'' | '"' => {
-                let string = self.read_string(ch);
-                TokenType::String(string)
-            }
+            '\'' | '"' => match self.read_string(ch) {
+                Some(string) => TokenType::String(string),
+                None => TokenType::Illegal,
+            },
+
+            // Characters, like `a`
+            '`' => match (self.read(), self.read()) {
+                (Some(ch), Some('`')) if ch != '`' => TokenType::Char(ch),
+                _ => TokenType::Illegal,
+            },
 
             // Braces 'n stuff
             '(' => TokenType::LeftParen,
@@ -117,11 +187,7 @@ impl<'a> Lexer<'a> {
                     let ident = self.read_identifier(ch);
                     return token::lookup_keyword(ident.as_str());
                 } else if Self::is_digit(ch) {
-                    let number = self.read_number(ch);
-                    match number {
-                        Some(number) => return TokenType::Number(number),
-                        None => return TokenType::Illegal,
-                    }
+                    return self.read_number(ch).unwrap_or(TokenType::Illegal);
                 }
                 TokenType::Illegal
             }
@@ -130,8 +196,9 @@ impl<'a> Lexer<'a> {
 
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        // Positions are character offsets from 0, covering `start..end` like a slice
+        let start = self.offset;
         if let Some(ch) = self.read() {
-            let start = self.offset;
             // Light abstraction to make this less ugly
             let tok = self.generate_token(ch);
             let end = self.offset;
@@ -140,9 +207,10 @@ impl<'a> Lexer<'a> {
                 position: start..end,
             };
         }
+        // Eof sits just past the last character, so errors about it point at the end of the input
         Token {
             tok: TokenType::Eof,
-            position: 0..0,
+            position: self.offset..self.offset,
         }
     }
 
@@ -193,7 +261,8 @@ impl<'a> Lexer<'a> {
         Number(5), Period, Number(6)
       That unambiguously translates to Number(5.6) (i hope)
     */
-    fn read_number(&mut self, initial: char) -> Option<f64> {
+    // Numbers without a decimal point are integers, unless they're too big for one
+    fn read_number(&mut self, initial: char) -> Option<TokenType> {
         let mut number = String::from(initial);
         let mut dot = false;
         while self.peek_fn(&Self::is_digit)
@@ -211,26 +280,24 @@ impl<'a> Lexer<'a> {
                 number.push(ch)
             }
         }
-        match number.parse() {
-            Ok(num) => Some(num),
-            Err(_) => None,
+        if !dot {
+            if let Ok(integer) = number.parse() {
+                return Some(TokenType::Integer(integer));
+            }
         }
+        number.parse().ok().map(TokenType::Number)
     }
     // TODO: Add support for escapes, like \"
-    fn read_string(&mut self, initial: char) -> String {
+    // Gives back None if the input ends before the string does
+    fn read_string(&mut self, initial: char) -> Option<String> {
         let mut string = String::new();
         // We use the initial character to support strings that use single or double quotes
         while self.peek_not(initial) {
-            if let Some(ch) = self.read() {
-                string.push(ch);
-            } else {
-                // TODO: Add proper error handling
-                panic!("Reached end of input before string was terminated")
-            }
+            string.push(self.read()?);
         }
         // Read the last quote
         self.read();
-        string
+        Some(string)
     }
     fn skip_whitespace(&mut self) {
         while self.peek_fn(&Self::is_whitespace) {
@@ -238,3 +305,48 @@ impl<'a> Lexer<'a> {
         }
     }
 }
+
+/// Updates `tokens`, lexed from the source before `edit`, to match `source`
+///
+/// Only the tokens around the edit are lexed again. Once a new token lines up
+/// with an old one after the edit, the rest of the old tokens are reused, shifted
+/// by the change in length
+pub fn relex(source: &str, tokens: &[Token], edit: &Edit) -> Vec<Token> {
+    // A token right before the edit could be extended by it, like `-` becoming `->`
+    let kept = tokens
+        .iter()
+        .take_while(|token| token.position.end + LOOKAHEAD <= edit.start)
+        .count();
+    let mut result = tokens[..kept].to_vec();
+    let resume = result.last().map_or(0, |token| token.position.end);
+
+    let shift = |token: &Token| Token {
+        tok: token.tok.clone(),
+        position: token.position.start + edit.new_end - edit.old_end
+            ..token.position.end + edit.new_end - edit.old_end,
+    };
+    let mut old = kept;
+    let mut lexer = Lexer::starting_at(source, resume);
+    loop {
+        let token = lexer.next_token();
+        if token.tok == TokenType::Eof {
+            return result;
+        }
+        // Past the edit, the source is the same as before, so a token starting
+        // where an old one did will lex the same way, as will everything after it
+        if token.position.start >= edit.new_end {
+            // Old tokens inside the edit can't line up, and shifting them could underflow
+            while old < tokens.len()
+                && (tokens[old].position.start < edit.old_end
+                    || shift(&tokens[old]).position.start < token.position.start)
+            {
+                old += 1;
+            }
+            if old < tokens.len() && shift(&tokens[old]) == token {
+                result.extend(tokens[old..].iter().map(shift));
+                return result;
+            }
+        }
+        result.push(token);
+    }
+}
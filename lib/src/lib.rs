@@ -1,8 +1,48 @@
+// ParseError carries its source and context, so it's large on purpose
+#![allow(clippy::result_large_err)]
+
 pub mod ast;
 pub mod context;
 pub mod evaluation;
+pub mod format;
 pub mod lexer;
 pub mod location;
 pub mod parser;
+pub mod semantics;
 pub mod style;
 pub mod token;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub use evaluation::config::EvaluatorConfig;
+pub use evaluation::error::EvalError;
+pub use evaluation::object::BuiltinFunc;
+
+use evaluation::{env::Environment, object::Object, Evaluator};
+use lexer::Lexer;
+use parser::Parser;
+
+/// Lexes, parses, and evaluates `source` in a fresh environment, returning the final value
+pub fn eval_str<'a>(source: &str) -> Result<Object<'a>, String> {
+    eval_str_with_env(source, Rc::new(RefCell::new(Environment::new())))
+}
+
+/// Like `eval_str`, but evaluates in `env`, so bindings persist between calls
+pub fn eval_str_with_env<'a>(
+    source: &str,
+    env: Rc<RefCell<Environment<'a>>>,
+) -> Result<Object<'a>, String> {
+    let mut parser = Parser::new(Lexer::new(source), source.to_string());
+    let program = parser.parse_program().map_err(|errors| {
+        errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    })?;
+
+    Evaluator::new(env, EvaluatorConfig::default())
+        .eval_program(program)
+        .map_err(|error| error.to_string())
+}
@@ -1,7 +1,13 @@
 use std::fmt;
 use std::ops::Range;
 
-/// Range of offsets
+#[cfg(test)]
+#[path = "./location_test.rs"]
+mod location_test;
+
+/// Range of character offsets into the source
+///
+/// These count `char`s rather than bytes, so they line up with `Location::from`
 pub type Position = Range<usize>;
 
 #[derive(Debug, Clone, Copy)]
@@ -20,24 +26,21 @@ impl PreciseLocation {
 }
 
 impl Location {
+    /// The line and column, both counted from 1, of the character `offset` characters into `source`
+    ///
+    /// A newline belongs to the line it ends, and offsets at or past the end
+    /// of the input are just after its last character
     pub fn from(offset: usize, source: &str) -> Location {
-        let mut total = 0;
         let mut line = 1;
-        let mut column = 0;
+        let mut column = 1;
 
-        for ch in source.chars() {
+        for ch in source.chars().take(offset) {
             if ch == '\n' {
                 line += 1;
-                column = 0;
+                column = 1;
             } else {
                 column += 1;
             }
-
-            total += 1;
-
-            if offset < total {
-                break;
-            }
         }
 
         Location { line, column }
@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn test_location_from_offset() {
+    let source = "let x\n\n  y = 1\n";
+    let cases = vec![
+        // Offset, line, column
+        (0, 1, 1),
+        (4, 1, 5),
+        // The newline ending a line is still on that line
+        (5, 1, 6),
+        (6, 2, 1),
+        (7, 3, 1),
+        (9, 3, 3),
+        (13, 3, 7),
+        (14, 3, 8),
+        // Past the last newline is the start of a line that doesn't exist yet
+        (15, 4, 1),
+        (100, 4, 1),
+    ];
+    for (offset, line, column) in cases {
+        let location = Location::from(offset, source);
+        assert_eq!(
+            (location.line, location.column),
+            (line, column),
+            "at offset {}",
+            offset
+        );
+    }
+}
+
+#[test]
+fn test_location_counts_characters() {
+    // `é` is two bytes, but only one character
+    let source = "'é'\nlet é";
+    let location = Location::from(2, source);
+    assert_eq!((location.line, location.column), (1, 3));
+    let location = Location::from(8, source);
+    assert_eq!((location.line, location.column), (2, 5));
+}
+
+#[test]
+fn test_location_without_newline_at_end() {
+    let location = Location::from(3, "abc");
+    assert_eq!((location.line, location.column), (1, 4));
+    let location = Location::from(0, "");
+    assert_eq!((location.line, location.column), (1, 1));
+}
@@ -2,7 +2,7 @@ use std::{fmt, ops::Range};
 
 use crate::{
     context::{Context, Hint},
-    location::{Location, PreciseLocation},
+    location::Location,
     style::{red, yellow},
     token::TokenType,
 };
@@ -114,6 +114,21 @@ impl ParseError {
     }
 }
 
+impl ParseError {
+    // How many characters to underline, which is the whole token unless it continues onto
+    // another line. The end of the input is still one wide
+    fn underline_width(&self) -> usize {
+        let width = self.position.end.saturating_sub(self.position.start);
+        let rest_of_line = self
+            .source
+            .chars()
+            .skip(self.position.start)
+            .take_while(|&ch| ch != '\n')
+            .count();
+        width.min(rest_of_line).max(1)
+    }
+}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let hint = match self.hint.clone() {
@@ -121,31 +136,19 @@ impl fmt::Display for ParseError {
             None => String::new(),
         };
 
-        let precise = PreciseLocation::new(
-            Location::from(self.position.start, self.source.as_str()),
-            Location::from(self.position.start, self.source.as_str()),
-        );
-
-        let lines: Vec<&str> = self.source.lines().collect();
-        let relevant = if precise.0.start.line == precise.0.end.line {
-            vec![*(lines.get(precise.0.start.line - 1).unwrap())]
-        } else {
-            Vec::from(
-                lines
-                    .get(precise.0.start.line - 1..=precise.0.end.line - 1)
-                    .unwrap(),
-            )
-        };
-        let mut current_line = precise.0.start.line;
-        for line in relevant.iter() {
-            writeln!(f, "{}|  {}", current_line, line)?;
-            current_line += 1;
-        }
-
-        let offset = ((current_line - 1).to_string().len() + 3) + (precise.0.start.column - 1);
-
-        let bump = String::from(" ").repeat(offset);
-        writeln!(f, "{}{}", bump, red("^"))?;
+        let location = Location::from(self.position.start, &self.source);
+        // The end of the input can be on a line of its own, after a trailing newline
+        let line = self
+            .source
+            .lines()
+            .nth(location.line - 1)
+            .unwrap_or_default();
+        let gutter = format!("{}|  ", location.line);
+        writeln!(f, "{}{}", gutter, line)?;
+
+        // Columns count from 1, so the caret goes after `column - 1` characters of the line
+        let bump = " ".repeat(gutter.len() + location.column - 1);
+        writeln!(f, "{}{}", bump, red("^".repeat(self.underline_width())))?;
         writeln!(f, "{}", self.kind)?;
 
         writeln!(f, "{}\n{}", hint, self.display_context())
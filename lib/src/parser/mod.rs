@@ -1,4 +1,4 @@
-use crate::ast::{BlockStatement, Expr, Ident, Pattern, Program, Stmt};
+use crate::ast::{BlockStatement, Expr, Ident, Parameter, Pattern, Program, Spanned, Stmt};
 use crate::context::{Context, Hint};
 use crate::lexer::Lexer;
 use crate::location::Position;
@@ -15,14 +15,20 @@ mod parser_test;
 #[derive(PartialEq, PartialOrd)]
 enum Precedence {
     Lowest,
+    Pipe,
     Logical,
     Equals,
     LessGreater,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Index,
     Range,
     Sum,
     Product,
     Modulus,
+    Power,
     Prefix,
     Match,
     Call,
@@ -30,6 +36,7 @@ enum Precedence {
 
 fn get_precedence(tok: &TokenType) -> Precedence {
     match tok {
+        TokenType::Pipe => Precedence::Pipe,
         TokenType::And | TokenType::Or => Precedence::Logical,
         TokenType::Eq => Precedence::Equals,
         TokenType::NotEq => Precedence::Equals,
@@ -39,6 +46,11 @@ fn get_precedence(tok: &TokenType) -> Precedence {
         TokenType::LessEq => Precedence::LessGreater,
         TokenType::GreaterEq => Precedence::LessGreater,
 
+        TokenType::BitOr => Precedence::BitOr,
+        TokenType::BitXor => Precedence::BitXor,
+        TokenType::BitAnd => Precedence::BitAnd,
+        TokenType::ShiftLeft | TokenType::ShiftRight => Precedence::Shift,
+
         TokenType::Range => Precedence::Range,
 
         TokenType::Plus => Precedence::Sum,
@@ -48,6 +60,7 @@ fn get_precedence(tok: &TokenType) -> Precedence {
         TokenType::Asterisk => Precedence::Product,
 
         TokenType::Modulus => Precedence::Modulus,
+        TokenType::Power => Precedence::Power,
 
         TokenType::Match => Precedence::Match,
 
@@ -59,20 +72,55 @@ fn get_precedence(tok: &TokenType) -> Precedence {
     }
 }
 
+// Patterns compare numbers by value, so integers and floats are both just numbers there
+fn pattern_number(tok: &TokenType) -> Option<f64> {
+    match tok {
+        TokenType::Integer(num) => Some(*num as f64),
+        TokenType::Number(num) => Some(*num),
+        _ => None,
+    }
+}
+
+// Whether an expression is a member of a variable, like `a.b` or `a[0].c`
+fn is_member_target(expr: &Expr) -> bool {
+    match expr {
+        Expr::Member { object, .. } => {
+            matches!(object.node, Expr::Ident(_)) || is_member_target(&object.node)
+        }
+        _ => false,
+    }
+}
+
+// The infix operator a compound assignment like `+=` applies
+fn compound_operator(tok: &TokenType) -> Option<&'static str> {
+    match tok {
+        TokenType::PlusAssign => Some("+"),
+        TokenType::MinusAssign => Some("-"),
+        TokenType::AsteriskAssign => Some("*"),
+        TokenType::SlashAssign => Some("/"),
+        TokenType::ModulusAssign => Some("%"),
+        _ => None,
+    }
+}
+
 pub struct Parser<'a> {
     l: Lexer<'a>,
     current_token: Token,
     peek_token: Token,
     source: String,
+    // Brackets opened up to the current token that haven't been closed yet, so error recovery
+    // can tell the end of a statement from the end of one inside a block
+    open_brackets: Vec<TokenType>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(l: Lexer, source: String) -> Parser {
+    pub fn new(l: Lexer<'a>, source: String) -> Parser<'a> {
         let mut p = Parser {
             l,
             current_token: Token::new(),
             peek_token: Token::new(),
             source,
+            open_brackets: vec![],
         };
 
         p.next_token();
@@ -83,22 +131,82 @@ impl<'a> Parser<'a> {
     fn next_token(&mut self) {
         self.current_token = self.peek_token.clone();
         self.peek_token = self.l.next_token();
+        self.track_brackets();
+    }
+
+    // A closing bracket that doesn't match the last opened one is ignored,
+    // so a stray `)` can't end the block it's in
+    fn track_brackets(&mut self) {
+        let opening = match self.current_token.tok {
+            TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => {
+                self.open_brackets.push(self.current_token.tok.clone());
+                return;
+            }
+            TokenType::RightParen => TokenType::LeftParen,
+            TokenType::RightBrace => TokenType::LeftBrace,
+            TokenType::RightBracket => TokenType::LeftBracket,
+            _ => return,
+        };
+        if self.open_brackets.last() == Some(&opening) {
+            self.open_brackets.pop();
+        }
+    }
+
+    // The token after the peek token, without moving past anything
+    fn second_peek(&self) -> TokenType {
+        self.l.clone().next_token().tok
     }
 
     fn position(&self) -> Position {
         self.current_token.position.clone()
     }
 
-    pub fn parse_program(&mut self) -> ParseResult<Program> {
+    /// Parses every statement, recovering after errors so they can all be reported at once
+    pub fn parse_program(&mut self) -> Result<Program, Vec<ParseError>> {
         let mut stmts = Program::new();
+        let mut errors = vec![];
         while self.current_token.tok != TokenType::Eof {
-            let stmt = self.parse_stmt().context("Parsing program")?;
-            stmts.0.push(stmt);
+            match self.parse_stmt().context("Parsing program") {
+                Ok(stmt) => stmts.0.push(stmt),
+                Err(error) => {
+                    errors.push(error);
+                    self.synchronize();
+                    continue;
+                }
+            }
             self.next_token();
         }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
         Ok(stmts)
     }
 
+    // Skips the rest of a broken statement, stopping at the start of the next one
+    // Statements inside brackets, like in a function's body, are skipped along with it
+    fn synchronize(&mut self) {
+        while !self.current_token_is(&TokenType::Eof) {
+            let top_level = self.open_brackets.is_empty();
+            if top_level && self.current_token_is(&TokenType::Semicolon) {
+                self.next_token();
+                return;
+            }
+            let at_boundary = top_level
+                && matches!(
+                    self.peek_token.tok,
+                    TokenType::Let
+                        | TokenType::Return
+                        | TokenType::Break
+                        | TokenType::Continue
+                        | TokenType::Import
+                );
+            self.next_token();
+            if at_boundary {
+                return;
+            }
+        }
+    }
+
     fn parse_pattern(&mut self) -> ParseResult<Pattern> {
         let pattern = match self.current_token.clone().tok {
             TokenType::Ident(id) => {
@@ -112,26 +220,51 @@ impl<'a> Parser<'a> {
             TokenType::String(str) => Pattern::String(str),
             TokenType::True => Pattern::Boolean(true),
             TokenType::False => Pattern::Boolean(false),
+            TokenType::Integer(_) | TokenType::Number(_)
+                if self.peek_token_is(&TokenType::Range) =>
+            {
+                let start = pattern_number(&self.current_token.tok).unwrap_or_default();
+                self.next_token();
+                self.next_token();
+                match pattern_number(&self.current_token.tok) {
+                    Some(end) => Pattern::Range(start, end),
+                    None => {
+                        return Err(ParseError::new(
+                            ParseErrorKind::ExpectedFound {
+                                expected: TokenType::Number(0.0),
+                                found: self.current_token.tok.clone(),
+                            },
+                            self.position(),
+                            self.source.clone(),
+                        )
+                        .context("Parsing range pattern"))
+                    }
+                }
+            }
+            TokenType::Integer(num) => Pattern::Number(num as f64),
             TokenType::Number(num) => Pattern::Number(num),
             TokenType::LeftBracket => {
                 let mut items = vec![];
                 if self.peek_token_is(&TokenType::RightBracket) {
-                    self.next_token();
                     self.next_token();
                     Pattern::Array(items)
                 } else {
                     self.next_token();
                     let value = self
-                        .parse_pattern()
+                        .parse_array_pattern_item()
                         .context("Parsing array pattern")
                         .context("Parsing pattern")?;
                     items.push(value);
 
                     while self.peek_token_is(&TokenType::Comma) {
                         self.next_token();
+                        // Allow a trailing comma
+                        if self.peek_token_is(&TokenType::RightBracket) {
+                            break;
+                        }
                         self.next_token();
                         let value = self
-                            .parse_pattern()
+                            .parse_array_pattern_item()
                             .context("Parsing array pattern")
                             .context("Parsing pattern")?;
                         items.push(value);
@@ -152,27 +285,28 @@ impl<'a> Parser<'a> {
             TokenType::LeftBrace => {
                 let mut items = vec![];
                 if self.peek_token_is(&TokenType::RightBrace) {
-                    self.next_token();
                     self.next_token();
                     Pattern::Hash(items)
                 } else {
                     self.next_token();
                     let value = self
-                        .parse_identifier()
-                        .context("Parsing hash identifier")
+                        .parse_hash_pattern_item()
                         .context("Parsing hash pattern")
                         .context("Parsing pattern")?;
-                    items.push((value, None));
+                    items.push(value);
 
                     while self.peek_token_is(&TokenType::Comma) {
                         self.next_token();
+                        // Allow a trailing comma
+                        if self.peek_token_is(&TokenType::RightBrace) {
+                            break;
+                        }
                         self.next_token();
                         let value = self
-                            .parse_identifier()
-                            .context("Parsing hash identifier")
+                            .parse_hash_pattern_item()
                             .context("Parsing hash pattern")
                             .context("Parsing pattern")?;
-                        items.push((value, None));
+                        items.push(value);
                     }
 
                     // Read past the RightBracket
@@ -197,10 +331,58 @@ impl<'a> Parser<'a> {
         Ok(pattern)
     }
 
+    // A key, optionally followed by the name to bind it to, like `name: n`
+    fn parse_hash_pattern_item(&mut self) -> ParseResult<(Ident, Option<Ident>)> {
+        let key = self.parse_identifier().context("Parsing hash identifier")?;
+        match self.peek_token.tok.clone() {
+            TokenType::Colon => {
+                self.next_token();
+                self.next_token();
+                let alias = self.parse_identifier().context("Parsing hash alias")?;
+                Ok((key, Some(alias)))
+            }
+            // Without a space, `name:n` is lexed as an identifier and a symbol
+            TokenType::Symbol(alias) => {
+                self.next_token();
+                Ok((key, Some(Ident(alias))))
+            }
+            _ => Ok((key, None)),
+        }
+    }
+
+    // Array patterns can also end with a rest pattern, like `[first, ...rest]`
+    fn parse_array_pattern_item(&mut self) -> ParseResult<Pattern> {
+        if !self.current_token_is(&TokenType::Spread) {
+            return self.parse_pattern();
+        }
+        let position = self.position();
+        self.next_token();
+        let ident = self.parse_identifier().context("Parsing rest pattern")?;
+        if self.peek_token_is(&TokenType::Comma) {
+            return Err(ParseError::new(
+                ParseErrorKind::UnsupportedToken(TokenType::Spread),
+                position,
+                self.source.clone(),
+            )
+            .context("Parsing rest pattern")
+            .hint("A rest pattern has to be the last item in an array pattern"));
+        }
+        Ok(Pattern::Rest(ident))
+    }
+
     fn parse_stmt(&mut self) -> ParseResult<Stmt> {
         match self.current_token.clone().tok {
             TokenType::Let => self.parse_assign_stmt(),
             TokenType::Return => self.parse_return_stmt(),
+            TokenType::Break => Ok(self.parse_loop_control_stmt(Stmt::Break)),
+            TokenType::Continue => Ok(self.parse_loop_control_stmt(Stmt::Continue)),
+            // `fn x -> x` is a function literal, but `fn name(x) -> x` names one
+            TokenType::Function
+                if matches!(self.peek_token.tok, TokenType::Ident(_))
+                    && self.second_peek() == TokenType::LeftParen =>
+            {
+                self.parse_function_stmt()
+            }
             TokenType::Import => self.parse_import_stmt(),
             _ => self.parse_expression_stmt(),
         }
@@ -235,6 +417,25 @@ impl<'a> Parser<'a> {
         }
         Ok(Stmt::Return(value))
     }
+    // `break` and `continue` are just the keyword, with an optional semicolon
+    fn parse_loop_control_stmt(&mut self, stmt: Stmt) -> Stmt {
+        if self.peek_token_is(&TokenType::Semicolon) {
+            self.next_token();
+        }
+        stmt
+    }
+    // `fn name(params) -> body` is the same as `let name = fn (params) -> body`
+    fn parse_function_stmt(&mut self) -> ParseResult<Stmt> {
+        self.next_token();
+        let name = self.parse_identifier().context("Parsing function name")?;
+        let function = self
+            .parse_function()
+            .context("Parsing function statement")?;
+        if self.peek_token_is(&TokenType::Semicolon) {
+            self.next_token();
+        }
+        Ok(Stmt::Assign(Pattern::Ident(name), function))
+    }
     fn parse_import_stmt(&mut self) -> ParseResult<Stmt> {
         self.next_token();
         let name = self
@@ -245,31 +446,57 @@ impl<'a> Parser<'a> {
             .context("Parsing import statement")?;
         self.next_token();
         let source = self.parse_expression(Precedence::Lowest)?;
+        let alias = if self.peek_token_is(&TokenType::As) {
+            self.next_token();
+            self.next_token();
+            Some(
+                self.parse_pattern()
+                    .context("Parsing import alias")
+                    .context("Parsing import")?,
+            )
+        } else {
+            None
+        };
         if self.peek_token_is(&TokenType::Semicolon) {
             self.next_token();
         }
-        Ok(Stmt::Import { source, name })
+        Ok(Stmt::Import {
+            source,
+            name,
+            alias,
+        })
     }
     fn parse_expression_stmt(&mut self) -> ParseResult<Stmt> {
-        let expression = self.parse_expression(Precedence::Lowest)?;
+        let expression = self.parse_spanned_expression(Precedence::Lowest)?;
         if self.peek_token_is(&TokenType::Semicolon) {
             self.next_token()
         }
         Ok(Stmt::Expr(expression))
     }
 
+    // Like parse_expression, but keeps track of where the expression came from
+    fn parse_spanned_expression(&mut self, precedence: Precedence) -> ParseResult<Spanned<Expr>> {
+        let start = self.position().start;
+        let node = self.parse_expression(precedence)?;
+        Ok(Spanned::new(node, start..self.position().end))
+    }
+
     fn parse_expression(&mut self, precedence: Precedence) -> ParseResult<Expr> {
+        let start = self.position().start;
         let token = self.current_token.clone();
         let mut left = match token.tok {
             TokenType::Ident(_) => self.parse_identifier().map(Expr::Ident),
             TokenType::String(_) => self.parse_string(),
             TokenType::Symbol(_) => self.parse_symbol(),
-            TokenType::Number(_) => self.parse_number(),
+            TokenType::Char(value) => Ok(Expr::Char(value)),
+            TokenType::Integer(_) | TokenType::Number(_) => self.parse_number(),
             TokenType::Bang => self.parse_prefix_expression(),
             TokenType::Minus => self.parse_prefix_expression(),
             TokenType::True | TokenType::False => Ok(self.parse_boolean()),
+            TokenType::Null => Ok(Expr::Null),
             TokenType::LeftParen => self.parse_grouped_expressions(),
             TokenType::If => self.parse_if_expression(),
+            TokenType::While => self.parse_while_expression(),
             TokenType::Function => self.parse_function(),
             TokenType::LeftBracket => self.parse_array(),
             TokenType::LeftBrace => self.parse_hash(),
@@ -277,7 +504,21 @@ impl<'a> Parser<'a> {
                 return Err(self.no_prefix_parser_error(token));
             }
         };
+        // Assignment binds the loosest, so only allow it at the start of an expression
+        if precedence == Precedence::Lowest {
+            if let Ok(Expr::Ident(name)) = &left {
+                if self.peek_token_is(&TokenType::Assign) {
+                    return self.parse_assign_expression(name.clone());
+                }
+                if let Some(operator) = compound_operator(&self.peek_token.tok) {
+                    return self.parse_compound_assign_expression(name.clone(), operator);
+                }
+            }
+        }
         while !self.peek_token_is(&TokenType::Semicolon) && precedence < self.peek_precedence() {
+            // The current token ends the left side, since the operator is still being peeked
+            let span = start..self.position().end;
+            let left_spanned = |left: ParseResult<Expr>| left.map(|left| Spanned::new(left, span));
             left = match self.peek_token.tok {
                 TokenType::Plus
                 | TokenType::Minus
@@ -285,6 +526,7 @@ impl<'a> Parser<'a> {
                 | TokenType::Asterisk
                 | TokenType::Slash
                 | TokenType::Modulus
+                | TokenType::Power
                 | TokenType::Eq
                 | TokenType::NotEq
                 | TokenType::Greater
@@ -292,34 +534,117 @@ impl<'a> Parser<'a> {
                 | TokenType::And
                 | TokenType::Or
                 | TokenType::Less
-                | TokenType::LessEq => {
+                | TokenType::LessEq
+                | TokenType::BitAnd
+                | TokenType::BitOr
+                | TokenType::BitXor
+                | TokenType::ShiftLeft
+                | TokenType::ShiftRight => {
                     self.next_token();
-                    self.parse_infix_expression(left?)
+                    self.parse_infix_expression(left_spanned(left)?)
                 }
                 TokenType::LeftParen => {
                     self.next_token();
-                    self.parse_call_expression(left?)
+                    self.parse_call_expression(left_spanned(left)?)
                 }
                 TokenType::LeftBracket => {
                     self.next_token();
                     self.next_token();
-                    self.parse_index_expression(left?)
+                    self.parse_index_expression(left_spanned(left)?)
                 }
                 TokenType::Match => {
                     self.next_token();
-                    self.parse_match(left?)
+                    self.parse_match(left_spanned(left)?)
+                }
+                TokenType::Pipe => {
+                    self.next_token();
+                    self.parse_pipe_expression(left_spanned(left)?)
                 }
                 TokenType::Period => {
                     self.next_token();
                     self.next_token();
-                    self.parse_dot_expression(left?)
+                    self.parse_dot_expression(left_spanned(left)?)
                 }
-                _ => return left,
+                _ => break,
             };
         }
+        // Members can be assigned too, like `a.b = c` or `a[i] += 1`
+        if precedence == Precedence::Lowest {
+            if let Ok(target) = &left {
+                if is_member_target(target) {
+                    let target = Spanned::new(target.clone(), start..self.position().end);
+                    if self.peek_token_is(&TokenType::Assign) {
+                        return self.parse_member_assign_expression(target, None);
+                    }
+                    if let Some(operator) = compound_operator(&self.peek_token.tok) {
+                        return self.parse_member_assign_expression(target, Some(operator));
+                    }
+                }
+            }
+        }
         left
     }
 
+    fn parse_member_assign_expression(
+        &mut self,
+        target: Spanned<Expr>,
+        operator: Option<&str>,
+    ) -> ParseResult<Expr> {
+        let start = self.position().start;
+        self.next_token();
+        self.next_token();
+        let value = self
+            .parse_spanned_expression(Precedence::Lowest)
+            .context("Parsing assignment value")?;
+        let value = match operator {
+            Some(operator) => Spanned::new(
+                Expr::Infix(
+                    Box::new(target.clone()),
+                    operator.to_string(),
+                    Box::new(value),
+                ),
+                start..self.position().end,
+            ),
+            None => value,
+        };
+        Ok(Expr::AssignMember {
+            target: Box::new(target),
+            value: Box::new(value),
+        })
+    }
+
+    fn parse_assign_expression(&mut self, name: Ident) -> ParseResult<Expr> {
+        self.next_token();
+        self.next_token();
+        let value = self
+            .parse_spanned_expression(Precedence::Lowest)
+            .context("Parsing assignment value")?;
+        Ok(Expr::Assign {
+            name,
+            value: Box::new(value),
+        })
+    }
+
+    // `x += e` is sugar for `x = x + e`
+    fn parse_compound_assign_expression(
+        &mut self,
+        name: Ident,
+        operator: &str,
+    ) -> ParseResult<Expr> {
+        let start = self.position().start;
+        let target = Spanned::new(Expr::Ident(name.clone()), self.position());
+        self.next_token();
+        self.next_token();
+        let value = self
+            .parse_spanned_expression(Precedence::Lowest)
+            .context("Parsing assignment value")?;
+        let value = Expr::Infix(Box::new(target), operator.to_string(), Box::new(value));
+        Ok(Expr::Assign {
+            name,
+            value: Box::new(Spanned::new(value, start..self.position().end)),
+        })
+    }
+
     fn parse_identifier(&mut self) -> ParseResult<Ident> {
         if let TokenType::Ident(ident) = self.current_token.clone().tok {
             return Ok(Ident(ident));
@@ -334,10 +659,11 @@ impl<'a> Parser<'a> {
         ))
     }
     fn parse_number(&mut self) -> ParseResult<Expr> {
-        if let TokenType::Number(num) = self.current_token.clone().tok {
-            return Ok(Expr::Number(num));
+        match self.current_token.tok {
+            TokenType::Integer(num) => Ok(Expr::Integer(num)),
+            TokenType::Number(num) => Ok(Expr::Number(num)),
+            _ => unreachable!(),
         }
-        unreachable!()
     }
     fn parse_boolean(&mut self) -> Expr {
         let value = self.current_token_is(&TokenType::True);
@@ -361,19 +687,25 @@ impl<'a> Parser<'a> {
             TokenType::Minus => "-",
             _ => "",
         };
+        // Powers bind tighter than negation, so `-2 ** 2` is `-(2 ** 2)`
+        let precedence = match operator {
+            "-" => Precedence::Modulus,
+            _ => Precedence::Prefix,
+        };
         self.next_token();
         let right = self
-            .parse_expression(Precedence::Prefix)
+            .parse_spanned_expression(precedence)
             .context("Parsing prefix expression")?;
         Ok(Expr::Prefix(operator.to_string(), Box::new(right)))
     }
-    fn parse_infix_expression(&mut self, left: Expr) -> ParseResult<Expr> {
+    fn parse_infix_expression(&mut self, left: Spanned<Expr>) -> ParseResult<Expr> {
         let operator = match self.current_token.tok {
             TokenType::Plus => "+",
             TokenType::Minus => "-",
             TokenType::Asterisk => "*",
             TokenType::Slash => "/",
             TokenType::Modulus => "%",
+            TokenType::Power => "**",
             TokenType::Eq => "==",
             TokenType::NotEq => "!=",
             TokenType::Greater => ">",
@@ -383,12 +715,21 @@ impl<'a> Parser<'a> {
             TokenType::Range => "..",
             TokenType::And => "&&",
             TokenType::Or => "||",
+            TokenType::BitAnd => "&",
+            TokenType::BitOr => "|",
+            TokenType::BitXor => "^",
+            TokenType::ShiftLeft => "<<",
+            TokenType::ShiftRight => ">>",
             _ => "",
         };
 
-        let precedence = self.current_precedence();
+        let precedence = match self.current_precedence() {
+            // Powers are right associative, so the right side can hold another `**`
+            Precedence::Power => Precedence::Modulus,
+            precedence => precedence,
+        };
         self.next_token();
-        let right = self.parse_expression(precedence)?;
+        let right = self.parse_spanned_expression(precedence)?;
 
         Ok(Expr::Infix(
             Box::new(left),
@@ -396,6 +737,40 @@ impl<'a> Parser<'a> {
             Box::new(right),
         ))
     }
+    // Pipes are sugar for calls, `x |> f(a)` becomes `f(x, a)`
+    // A `_` argument places the value somewhere else, `x |> f(a, _)` becomes `f(a, x)`
+    fn parse_pipe_expression(&mut self, left: Spanned<Expr>) -> ParseResult<Expr> {
+        let precedence = self.current_precedence();
+        self.next_token();
+        let right = self
+            .parse_spanned_expression(precedence)
+            .context("Parsing pipe expression")?;
+        let left = left.node;
+
+        Ok(match right.node {
+            Expr::Call {
+                function,
+                mut arguments,
+            } => {
+                let placeholder = Expr::Ident(Ident::from("_"));
+                match arguments.iter().position(|arg| arg == &placeholder) {
+                    Some(index) => arguments[index] = left,
+                    None => arguments.insert(0, left),
+                }
+                Expr::Call {
+                    function,
+                    arguments,
+                }
+            }
+            function => Expr::Call {
+                function: Box::new(Spanned {
+                    node: function,
+                    span: right.span,
+                }),
+                arguments: vec![left],
+            },
+        })
+    }
     fn parse_grouped_expressions(&mut self) -> ParseResult<Expr> {
         self.next_token();
         let exp = self
@@ -408,7 +783,7 @@ impl<'a> Parser<'a> {
     }
     fn parse_if_expression(&mut self) -> ParseResult<Expr> {
         self.next_token();
-        let condition = self.parse_expression(Precedence::Lowest)?;
+        let condition = self.parse_spanned_expression(Precedence::Lowest)?;
         // TODO: Support shortened versions like this:
         //  if true then 5 else 10
         self.expect_peek(&TokenType::LeftBrace)?;
@@ -423,16 +798,27 @@ impl<'a> Parser<'a> {
             alternative,
         })
     }
+    fn parse_while_expression(&mut self) -> ParseResult<Expr> {
+        self.next_token();
+        let condition = self.parse_spanned_expression(Precedence::Lowest)?;
+        self.expect_peek(&TokenType::LeftBrace)?;
+        let body = self.parse_block_stmt()?;
+
+        Ok(Expr::While {
+            condition: Box::new(condition),
+            body,
+        })
+    }
     fn parse_function(&mut self) -> ParseResult<Expr> {
         let token = self.peek_token.clone();
-        let parameters = match token.tok {
+        let (parameters, rest) = match token.tok {
             TokenType::LeftParen => {
                 self.next_token();
                 self.parse_function_parameters()?
             }
             TokenType::Ident(ident) => {
                 self.next_token();
-                vec![Ident(ident)]
+                (vec![(Ident(ident), None)], None)
             }
             tok => {
                 return Err(ParseError::new(
@@ -454,42 +840,79 @@ impl<'a> Parser<'a> {
 
         let body = self.parse_block_shorthand()?;
 
-        Ok(Expr::Function { parameters, body })
+        Ok(Expr::Function {
+            parameters,
+            rest,
+            body,
+        })
     }
-    fn parse_function_parameters(&mut self) -> ParseResult<Vec<Ident>> {
-        let mut identifiers = vec![];
+    // Parses the parameter list, along with a trailing rest parameter if there is one
+    fn parse_function_parameters(&mut self) -> ParseResult<(Vec<Parameter>, Option<Ident>)> {
+        let mut parameters = vec![];
+        let mut rest = None;
 
         if self.peek_token_is(&TokenType::RightParen) {
             self.next_token();
-            return Ok(identifiers);
+            return Ok((parameters, rest));
         }
 
-        self.next_token();
-
-        let ident = match &self.current_token.tok {
-            TokenType::Ident(name) => Ident(name.clone()),
-            _ => Ident(String::new()),
-        };
-        identifiers.push(ident);
-
-        while self.peek_token_is(&TokenType::Comma) {
+        loop {
             self.next_token();
+            if self.current_token_is(&TokenType::Spread) {
+                self.next_token();
+                rest = Some(
+                    self.parse_identifier()
+                        .context("Parsing rest parameter")
+                        .context("Parsing function parameters")?,
+                );
+                // Nothing can follow the rest parameter, so fall through to expecting `)`
+                break;
+            }
+            // Once one parameter has a default, the rest need one too
+            let needs_default = parameters.iter().any(|(_, default)| default.is_some());
+            parameters.push(self.parse_function_parameter(needs_default)?);
+            if !self.peek_token_is(&TokenType::Comma) {
+                break;
+            }
             self.next_token();
-            let ident = match &self.current_token.tok {
-                TokenType::Ident(name) => Ident(name.clone()),
-                _ => Ident(String::new()),
-            };
-            identifiers.push(ident);
+            // Allow a trailing comma
+            if self.peek_token_is(&TokenType::RightParen) {
+                break;
+            }
         }
 
         self.expect_peek(&TokenType::RightParen)
             .context("Parsing closing paren")
-            .context("Parsing function parameters")?;
+            .context("Parsing function parameters")
+            .hint("A rest parameter has to be the last parameter")?;
 
-        Ok(identifiers)
+        Ok((parameters, rest))
+    }
+    // A parameter name, optionally followed by a default value like `y = 10`
+    fn parse_function_parameter(&mut self, needs_default: bool) -> ParseResult<Parameter> {
+        let ident = match &self.current_token.tok {
+            TokenType::Ident(name) => Ident(name.clone()),
+            _ => Ident(String::new()),
+        };
+        if !self.peek_token_is(&TokenType::Assign) {
+            if needs_default {
+                return Err(self
+                    .peek_error(&TokenType::Assign)
+                    .context("Parsing function parameters")
+                    .hint("Parameters with default values have to come after the ones without"));
+            }
+            return Ok((ident, None));
+        }
+        self.next_token();
+        self.next_token();
+        let default = self
+            .parse_expression(Precedence::Lowest)
+            .context("Parsing default parameter value")
+            .context("Parsing function parameters")?;
+        Ok((ident, Some(default)))
     }
-    fn parse_index_expression(&mut self, left: Expr) -> ParseResult<Expr> {
-        let index = self.parse_expression(Precedence::Lowest)?;
+    fn parse_index_expression(&mut self, left: Spanned<Expr>) -> ParseResult<Expr> {
+        let index = self.parse_spanned_expression(Precedence::Lowest)?;
 
         self.next_token();
         if !self.current_token_is(&TokenType::RightBracket) {
@@ -503,24 +926,32 @@ impl<'a> Parser<'a> {
             ));
         };
 
-        Ok(Expr::Member {
-            property: Box::new(index),
-            object: Box::new(left),
-            computed: true,
-        })
+        match index.node {
+            // An index that's a range, like `a[1..3]`, takes a slice instead
+            Expr::Infix(start, operator, end) if operator == ".." => Ok(Expr::Slice {
+                object: Box::new(left),
+                start,
+                end,
+            }),
+            _ => Ok(Expr::Member {
+                property: Box::new(index),
+                object: Box::new(left),
+                computed: true,
+            }),
+        }
     }
 
-    fn parse_dot_expression(&mut self, left: Expr) -> ParseResult<Expr> {
+    fn parse_dot_expression(&mut self, left: Spanned<Expr>) -> ParseResult<Expr> {
         let property = self.parse_identifier()?;
 
         Ok(Expr::Member {
-            property: Box::new(property.into()),
+            property: Box::new(Spanned::new(property.into(), self.position())),
             object: Box::new(left),
             computed: false,
         })
     }
 
-    fn parse_call_expression(&mut self, function: Expr) -> ParseResult<Expr> {
+    fn parse_call_expression(&mut self, function: Spanned<Expr>) -> ParseResult<Expr> {
         let args = self
             .parse_call_expression_args()
             .context("Parsing call expression")?;
@@ -543,6 +974,10 @@ impl<'a> Parser<'a> {
 
         while self.peek_token_is(&TokenType::Comma) {
             self.next_token();
+            // Allow a trailing comma
+            if self.peek_token_is(&TokenType::RightParen) {
+                break;
+            }
             self.next_token();
             args.push(self.parse_expression(Precedence::Lowest)?);
         }
@@ -573,11 +1008,11 @@ impl<'a> Parser<'a> {
         if self.current_token_is(&TokenType::LeftBrace) {
             return self.parse_block_stmt();
         }
-        let expr = self.parse_expression(Precedence::Lowest)?;
+        let expr = self.parse_spanned_expression(Precedence::Lowest)?;
         Ok(Stmt::Expr(expr).into())
     }
 
-    fn parse_match(&mut self, condition: Expr) -> ParseResult<Expr> {
+    fn parse_match(&mut self, condition: Spanned<Expr>) -> ParseResult<Expr> {
         self.expect_peek(&TokenType::LeftBrace)?;
         let cases = self.parse_match_cases()?;
         self.expect_peek(&TokenType::RightBrace)
@@ -589,14 +1024,38 @@ impl<'a> Parser<'a> {
             cases,
         })
     }
-    fn parse_match_cases(&mut self) -> ParseResult<Vec<(Pattern, BlockStatement)>> {
+    fn parse_match_cases(&mut self) -> ParseResult<Vec<(Pattern, Option<Expr>, BlockStatement)>> {
         let mut cases = vec![];
 
         if self.peek_token_is(&TokenType::RightBrace) {
             return Ok(cases);
         }
         self.next_token();
-        let key = self.parse_pattern()?;
+        cases.push(self.parse_match_case()?);
+
+        while self.peek_token_is(&TokenType::Comma) {
+            self.next_token();
+            // Allow a trailing comma
+            if self.peek_token_is(&TokenType::RightBrace) {
+                break;
+            }
+            self.next_token();
+            cases.push(self.parse_match_case()?);
+        }
+        Ok(cases)
+    }
+    fn parse_match_case(&mut self) -> ParseResult<(Pattern, Option<Expr>, BlockStatement)> {
+        let key = self.parse_pattern().context("Parsing match expression")?;
+        let guard = if self.peek_token_is(&TokenType::If) {
+            self.next_token();
+            self.next_token();
+            Some(
+                self.parse_expression(Precedence::Lowest)
+                    .context("Parsing match guard")?,
+            )
+        } else {
+            None
+        };
         self.expect_peek(&TokenType::Arrow)
             .context("Parsing match case")
             .hint(
@@ -610,28 +1069,7 @@ true :: {
         self.next_token();
         let value = self.parse_block_shorthand()?;
 
-        cases.push((key, value));
-
-        while self.peek_token_is(&TokenType::Comma) {
-            self.next_token();
-            self.next_token();
-            let key = self.parse_pattern().context("Parsing match expression")?;
-            self.expect_peek(&TokenType::Arrow)
-                .context("Parsing match case")
-                .hint(
-                    "Make sure you follow valid match syntax, here's an example:
-true :: {
-    true -> 'yes',
-    false -> 'no'    
-}
-",
-                )?;
-            self.next_token();
-            let value = self.parse_block_shorthand()?;
-
-            cases.push((key, value));
-        }
-        Ok(cases)
+        Ok((key, guard, value))
     }
 
     fn parse_array(&mut self) -> ParseResult<Expr> {
@@ -647,6 +1085,10 @@ true :: {
 
         while self.peek_token_is(&TokenType::Comma) {
             self.next_token();
+            // Allow a trailing comma
+            if self.peek_token_is(&TokenType::RightBracket) {
+                break;
+            }
             self.next_token();
             let value = self.parse_expression(Precedence::Lowest)?;
             items.push(value);
@@ -668,47 +1110,16 @@ true :: {
         }
 
         self.next_token();
-        let key = self.parse_expression(Precedence::Lowest)?;
-        match key.clone() {
-            Expr::Ident(ident) => {
-                // Short hand like this
-                // { foo, bar = 5 }
-                // The value of the foo key is the value of the variable foo
-                if self.peek_token_is(&TokenType::Comma)
-                    || self.peek_token_is(&TokenType::RightBrace)
-                {
-                    items.push((ident, key))
-                } else {
-                    self.expect_peek(&TokenType::Assign)
-                        .context("Parsing hash value")
-                        .context("Parsing hash")?;
-                    self.next_token();
-                    let value = self.parse_expression(Precedence::Lowest)?;
-                    items.push((ident, value));
-                }
-            }
-            _ => return Ok(Expr::Hash(items)),
-        }
+        items.push(self.parse_hash_item()?);
 
         while self.peek_token_is(&TokenType::Comma) {
             self.next_token();
-            self.next_token();
-            let key = self.parse_expression(Precedence::Lowest)?;
-            match key.clone() {
-                Expr::Ident(ident) => {
-                    if self.peek_token_is(&TokenType::Comma) {
-                        items.push((ident, key))
-                    } else {
-                        self.expect_peek(&TokenType::Assign)
-                            .context("Parsing hash value")
-                            .context("Parsing hash")?;
-                        self.next_token();
-                        let value = self.parse_expression(Precedence::Lowest)?;
-                        items.push((ident, value));
-                    }
-                }
-                _ => return Ok(Expr::Hash(items)),
+            // Allow a trailing comma
+            if self.peek_token_is(&TokenType::RightBrace) {
+                break;
             }
+            self.next_token();
+            items.push(self.parse_hash_item()?);
         }
 
         // Read past the RightBracket
@@ -718,6 +1129,41 @@ true :: {
 
         Ok(Expr::Hash(items))
     }
+    fn parse_hash_item(&mut self) -> ParseResult<(Expr, Expr)> {
+        let key = match self.current_token.tok {
+            TokenType::Ident(_) => self.parse_identifier().map(Expr::Ident)?,
+            TokenType::String(_) => self.parse_string()?,
+            TokenType::Symbol(_) => self.parse_symbol()?,
+            TokenType::Integer(_) | TokenType::Number(_) => self.parse_number()?,
+            TokenType::True | TokenType::False => self.parse_boolean(),
+            _ => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnsupportedToken(self.current_token.tok.clone()),
+                    self.position(),
+                    self.source.clone(),
+                )
+                .context("Parsing hash key")
+                .context("Parsing hash")
+                .hint("Hash keys can be identifiers, strings, symbols, numbers, or booleans"))
+            }
+        };
+
+        // Short hand like this
+        // { foo, bar = 5 }
+        // The value of the foo key is the value of the variable foo
+        if let Expr::Ident(_) = key {
+            if self.peek_token_is(&TokenType::Comma) || self.peek_token_is(&TokenType::RightBrace) {
+                return Ok((key.clone(), key));
+            }
+        }
+
+        self.expect_peek(&TokenType::Assign)
+            .context("Parsing hash value")
+            .context("Parsing hash")?;
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        Ok((key, value))
+    }
 
     // Utils
     fn current_token_is(&mut self, t: &TokenType) -> bool {
@@ -731,7 +1177,7 @@ true :: {
             self.next_token();
             return Ok(());
         }
-        Err(self.peek_error(&t))
+        Err(self.peek_error(t))
     }
     fn peek_precedence(&mut self) -> Precedence {
         get_precedence(&self.peek_token.tok)
@@ -740,20 +1186,12 @@ true :: {
         get_precedence(&self.current_token.tok)
     }
     // Errors stuff
-    fn peek_error(&mut self, t: &TokenType) -> ParseError {
-        let t = Token {
-            tok: t.clone(),
-            position: match self.peek_token.tok {
-                TokenType::Eof => self.current_token.position.clone(),
-                _ => self.peek_token.position.clone(),
-            },
-        };
-
+    fn peek_error(&self, t: &TokenType) -> ParseError {
         let kind = ParseErrorKind::ExpectedFound {
-            expected: t.tok,
+            expected: t.clone(),
             found: self.peek_token.tok.clone(),
         };
-        ParseError::new(kind, t.position, self.source.clone())
+        ParseError::new(kind, self.peek_token.position.clone(), self.source.clone())
     }
     fn no_prefix_parser_error(&mut self, t: Token) -> ParseError {
         ParseError::new(
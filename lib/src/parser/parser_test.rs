@@ -1,6 +1,7 @@
 use super::*;
-use crate::ast::{Expr, Ident, Stmt};
+use crate::ast::{BlockStatement, Expr, Ident, Stmt};
 use crate::lexer::Lexer;
+use crate::token::Token;
 
 #[test]
 fn test_assign_stmt() {
@@ -10,13 +11,24 @@ fn test_assign_stmt() {
 	let foobar = 838383;
 	";
     let expected = vec![
-        Stmt::Assign(Ident::from("x").into(), Expr::Number(5.0)),
-        Stmt::Assign(Ident::from("y").into(), Expr::Number(10.0)),
-        Stmt::Assign(Ident::from("foobar").into(), Expr::Number(838383.0)),
+        Stmt::Assign(Ident::from("x").into(), Expr::Integer(5)),
+        Stmt::Assign(Ident::from("y").into(), Expr::Integer(10)),
+        Stmt::Assign(Ident::from("foobar").into(), Expr::Integer(838383)),
     ];
     test_output(input, expected)
 }
 #[test]
+fn test_char_expression() {
+    let input = "`a` + 'bc'";
+    let expected = vec![Expr::Infix(
+        Expr::Char('a').into(),
+        String::from("+"),
+        Expr::String("bc".to_string()).into(),
+    )
+    .into()];
+    test_output(input, expected)
+}
+#[test]
 fn test_return_stmt() {
     let input = "
 	return 5;
@@ -24,9 +36,9 @@ fn test_return_stmt() {
 	return 993322;
 	";
     let expected = vec![
-        Stmt::Return(Expr::Number(5.0)),
-        Stmt::Return(Expr::Number(10.0)),
-        Stmt::Return(Expr::Number(993322.0)),
+        Stmt::Return(Expr::Integer(5)),
+        Stmt::Return(Expr::Integer(10)),
+        Stmt::Return(Expr::Integer(993322)),
     ];
     test_output(input, expected);
 }
@@ -36,15 +48,23 @@ fn test_import_stmt() {
     let input = "
     import foo from 'foo';
     import bar from \"foobar\";
+    import baz from 'baz' as qux;
 	";
     let expected = vec![
         Stmt::Import {
             name: Ident("foo".to_string()).into(),
             source: Expr::String("foo".to_string()),
+            alias: None,
         },
         Stmt::Import {
             name: Ident("bar".to_string()).into(),
             source: Expr::String("foobar".to_string()),
+            alias: None,
+        },
+        Stmt::Import {
+            name: Ident("baz".to_string()).into(),
+            source: Expr::String("baz".to_string()),
+            alias: Some(Ident("qux".to_string()).into()),
         },
     ];
     test_output(input, expected)
@@ -52,7 +72,7 @@ fn test_import_stmt() {
 #[test]
 fn test_identifier_expression() {
     let input = "foobar;";
-    let expected: Vec<Stmt> = vec![Stmt::Expr(Ident::from("foobar").into())];
+    let expected: Vec<Stmt> = vec![Expr::from(Ident::from("foobar")).into()];
     test_output(input, expected)
 }
 #[test]
@@ -76,16 +96,16 @@ fn test_symbol_expression() {
 #[test]
 fn test_number_expression() {
     let input = "5;";
-    let expected = Expr::Number(5.0).into();
+    let expected = Expr::Integer(5).into();
     test_output(input, expected)
 }
 
 #[test]
 fn test_prefix_expression() {
-    let inputs = vec!["-5", "!5"];
+    let inputs = ["-5", "!5"];
     let outputs: Vec<Vec<Stmt>> = vec![
-        Expr::Prefix(String::from("-"), Box::new(Expr::Number(5.0))).into(),
-        Expr::Prefix(String::from("!"), Box::new(Expr::Number(5.0))).into(),
+        Expr::Prefix(String::from("-"), Expr::Integer(5).into()).into(),
+        Expr::Prefix(String::from("!"), Expr::Integer(5).into()).into(),
     ];
     inputs.iter().enumerate().for_each(|(index, input)| {
         let expected = outputs[index].clone();
@@ -99,21 +119,33 @@ fn test_boolean_expression() {
     test_output(input, expected)
 }
 
+#[test]
+fn test_null_expression() {
+    let input = "null; let x = null";
+    let expected = vec![
+        Expr::Null.into(),
+        Stmt::Assign(Ident::from("x").into(), Expr::Null),
+    ];
+    test_output(input, expected)
+}
+
 #[test]
 fn test_grouped_expressions() {
     let input = "1 * 5 + (5 / 2)";
     let expected = vec![Expr::Infix(
-        Box::new(Expr::Infix(
-            Box::new(Expr::Number(1.0)),
+        Expr::Infix(
+            Expr::Integer(1).into(),
             String::from("*"),
-            Box::new(Expr::Number(5.0)),
-        )),
+            Expr::Integer(5).into(),
+        )
+        .into(),
         String::from("+"),
-        Box::new(Expr::Infix(
-            Box::new(Expr::Number(5.0)),
+        Expr::Infix(
+            Expr::Integer(5).into(),
             String::from("/"),
-            Box::new(Expr::Number(2.0)),
-        )),
+            Expr::Integer(2).into(),
+        )
+        .into(),
     )
     .into()];
     test_output(input, expected)
@@ -123,9 +155,269 @@ fn test_grouped_expressions() {
 fn test_if_expression() {
     let input = "if true { 10 } else { 5 }";
     let expected = vec![Expr::If {
-        condition: Box::new(Expr::Boolean(true)),
-        consequence: Expr::Number(10.0).into(),
-        alternative: Expr::Number(5.0).into(),
+        condition: Expr::Boolean(true).into(),
+        consequence: Expr::Integer(10).into(),
+        alternative: Expr::Integer(5).into(),
+    }
+    .into()];
+    test_output(input, expected)
+}
+
+#[test]
+fn test_default_parameters() {
+    let input = "fn (x, y = 10) -> x + y";
+    let expected = vec![Expr::Function {
+        parameters: vec![
+            (Ident::from("x"), None),
+            (Ident::from("y"), Some(Expr::Integer(10))),
+        ],
+        rest: None,
+        body: Expr::Infix(
+            Expr::from(Ident::from("x")).into(),
+            String::from("+"),
+            Expr::from(Ident::from("y")).into(),
+        )
+        .into(),
+    }
+    .into()];
+    test_output(input, expected);
+
+    let input = "fn (x = 1, y) -> x";
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::ExpectedFound {
+            expected: TokenType::Assign,
+            found: TokenType::RightParen,
+        }
+    );
+}
+
+#[test]
+fn test_error_at_eof() {
+    // How far into the rendered line the caret is
+    fn caret_column(error: &ParseError) -> usize {
+        let rendered = error.to_string();
+        let caret = rendered.lines().nth(1).unwrap();
+        caret.len() - caret.trim_start().len()
+    }
+    let errors = Parser::new(Lexer::new("1 +"), "1 +".to_string())
+        .parse_program()
+        .unwrap_err();
+    assert_eq!(errors[0].position, 3..3);
+    // Just past the `+`, after the `1|  ` gutter
+    assert_eq!(caret_column(&errors[0]), "1|  1 +".len());
+
+    let input = "let y = [1, 2";
+    let errors = Parser::new(Lexer::new(input), input.to_string())
+        .parse_program()
+        .unwrap_err();
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::ExpectedFound {
+            expected: TokenType::RightBracket,
+            found: TokenType::Eof,
+        }
+    );
+    assert_eq!(caret_column(&errors[0]), "1|  ".len() + input.len());
+
+    // A trailing newline puts the end of the input on a line of its own
+    let errors = Parser::new(Lexer::new("[1, 2\n"), "[1, 2\n".to_string())
+        .parse_program()
+        .unwrap_err();
+    assert!(errors[0].to_string().starts_with("2|"), "{}", errors[0]);
+}
+
+#[test]
+fn test_underline_width() {
+    // How many carets are under the rendered line
+    fn underline(input: &str) -> usize {
+        let errors = Parser::new(Lexer::new(input), input.to_string())
+            .parse_program()
+            .unwrap_err();
+        let rendered = errors[0].to_string();
+        rendered.lines().nth(1).unwrap().matches('^').count()
+    }
+    // The whole keyword is underlined, not just its first character
+    assert_eq!(underline("let x = import"), "import".len());
+    assert_eq!(underline("let x 50"), "50".len());
+    assert_eq!(underline("let x = 1 +"), 1);
+    // A string running onto another line is only underlined up to the end of the first
+    assert_eq!(underline("let x 'ab\ncd'"), "'ab".len());
+}
+
+#[test]
+fn test_error_after_multibyte_characters() {
+    let input = "let s = 'é'; let x 5";
+    let errors = Parser::new(Lexer::new(input), input.to_string())
+        .parse_program()
+        .unwrap_err();
+    assert_eq!(errors[0].position, 19..20);
+    // The caret is counted in characters, so it still lines up under the `5`
+    let rendered = errors[0].to_string();
+    let caret = rendered.lines().nth(1).unwrap();
+    let column = caret.chars().count() - caret.trim_start().chars().count();
+    assert_eq!(column, "1|  let s = 'é'; let x ".chars().count());
+}
+
+#[test]
+fn test_peek_error() {
+    // The unexpected token is reported through the error, and points at where it was found
+    let input = "let x 5";
+    let errors = Parser::new(Lexer::new(input), input.to_string())
+        .parse_program()
+        .unwrap_err();
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::ExpectedFound {
+            expected: TokenType::Assign,
+            found: TokenType::Integer(5),
+        }
+    );
+    assert_eq!(errors[0].position, 6..7);
+    // The caret sits right under the `5`, after the `1|  ` gutter
+    let rendered = errors[0].to_string();
+    let caret = rendered.lines().nth(1).unwrap();
+    assert_eq!(caret.len() - caret.trim_start().len(), "1|  let x ".len());
+}
+
+#[test]
+fn test_function_stmt() {
+    let square = Expr::Function {
+        parameters: vec![(Ident::from("x"), None)],
+        rest: None,
+        body: Expr::Infix(
+            Expr::Ident(Ident::from("x")).into(),
+            String::from("*"),
+            Expr::Ident(Ident::from("x")).into(),
+        )
+        .into(),
+    };
+    test_output(
+        "fn square(x) -> x * x",
+        vec![Stmt::Assign(Ident::from("square").into(), square.clone())],
+    );
+    // Without parentheses, a name is still a single parameter
+    test_output(
+        "fn x -> x * x",
+        vec![Expr::Function {
+            parameters: vec![(Ident::from("x"), None)],
+            rest: None,
+            body: Expr::Infix(
+                Expr::Ident(Ident::from("x")).into(),
+                String::from("*"),
+                Expr::Ident(Ident::from("x")).into(),
+            )
+            .into(),
+        }
+        .into()],
+    );
+    test_output(
+        "fn square(x) -> x * x; square",
+        vec![
+            Stmt::Assign(Ident::from("square").into(), square),
+            Expr::Ident(Ident::from("square")).into(),
+        ],
+    );
+}
+
+#[test]
+fn test_rest_parameter() {
+    let input = "fn (first, ...rest) -> rest";
+    let expected = vec![Expr::Function {
+        parameters: vec![(Ident::from("first"), None)],
+        rest: Some(Ident::from("rest")),
+        body: Expr::Ident(Ident::from("rest")).into(),
+    }
+    .into()];
+    test_output(input, expected);
+
+    let input = "fn (...a, b) -> a";
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::ExpectedFound {
+            expected: TokenType::RightParen,
+            found: TokenType::Comma,
+        }
+    );
+}
+
+#[test]
+fn test_assign_expression() {
+    let input = "x = y = 1 + 2";
+    let expected = vec![Expr::Assign {
+        name: Ident::from("x"),
+        value: Expr::Assign {
+            name: Ident::from("y"),
+            value: Expr::Infix(
+                Expr::Integer(1).into(),
+                String::from("+"),
+                Expr::Integer(2).into(),
+            )
+            .into(),
+        }
+        .into(),
+    }
+    .into()];
+    test_output(input, expected);
+
+    // Hash entries still use `=`
+    let input = "{ x = 1 }";
+    let expected = vec![Expr::Hash(vec![(Ident::from("x").into(), Expr::Integer(1))]).into()];
+    test_output(input, expected)
+}
+
+#[test]
+fn test_compound_assign_expression() {
+    for operator in &["+", "-", "*", "/", "%"] {
+        let input = format!("x {}= 1 + 2", operator);
+        let expected = vec![Expr::Assign {
+            name: Ident::from("x"),
+            value: Expr::Infix(
+                Expr::from(Ident::from("x")).into(),
+                operator.to_string(),
+                Expr::Infix(
+                    Expr::Integer(1).into(),
+                    String::from("+"),
+                    Expr::Integer(2).into(),
+                )
+                .into(),
+            )
+            .into(),
+        }
+        .into()];
+        test_output(&input, expected);
+    }
+
+    // Only identifiers can be assigned to
+    let input = "1 += 2";
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    assert!(p.parse_program().is_err());
+}
+
+#[test]
+fn test_while_expression() {
+    let input = "while i < 10 { let i = i + 1 }";
+    let expected = vec![Expr::While {
+        condition: Expr::Infix(
+            Expr::from(Ident::from("i")).into(),
+            String::from("<"),
+            Expr::Integer(10).into(),
+        )
+        .into(),
+        body: BlockStatement(vec![Stmt::Assign(
+            Ident::from("i").into(),
+            Expr::Infix(
+                Expr::from(Ident::from("i")).into(),
+                String::from("+"),
+                Expr::Integer(1).into(),
+            ),
+        )]),
     }
     .into()];
     test_output(input, expected)
@@ -137,11 +429,12 @@ fn test_function_expression() {
         foo + bar
     }";
     let expected = vec![Expr::Function {
-        parameters: vec![Ident::from("foo"), Ident::from("bar")],
+        parameters: vec![(Ident::from("foo"), None), (Ident::from("bar"), None)],
+        rest: None,
         body: Expr::Infix(
-            Box::new(Ident::from("foo").into()),
+            Expr::from(Ident::from("foo")).into(),
             String::from("+"),
-            Box::new(Ident::from("bar").into()),
+            Expr::from(Ident::from("bar")).into(),
         )
         .into(),
     }
@@ -151,13 +444,13 @@ fn test_function_expression() {
 
 #[test]
 fn test_array_expression() {
-    let inputs = vec!["[ 1, 2, 3, 4]", "[[ true, false ]]", "[[ :ok, 10 ]]"];
+    let inputs = ["[ 1, 2, 3, 4]", "[[ true, false ]]", "[[ :ok, 10 ]]"];
     let outputs: Vec<Vec<Stmt>> = vec![
         Expr::Array(vec![
-            Expr::Number(1.0),
-            Expr::Number(2.0),
-            Expr::Number(3.0),
-            Expr::Number(4.0),
+            Expr::Integer(1),
+            Expr::Integer(2),
+            Expr::Integer(3),
+            Expr::Integer(4),
         ])
         .into(),
         Expr::Array(vec![Expr::Array(vec![
@@ -167,7 +460,7 @@ fn test_array_expression() {
         .into(),
         Expr::Array(vec![Expr::Array(vec![
             Expr::Symbol("ok".to_string()),
-            Expr::Number(10.0),
+            Expr::Integer(10),
         ])])
         .into(),
     ];
@@ -181,10 +474,42 @@ fn test_array_expression() {
 fn test_hash_expression() {
     let input = "{ name = 'bob', age = 15, height, status = :online }";
     let expected = Expr::Hash(vec![
-        (Ident::from("name"), Expr::from("bob")),
-        (Ident::from("age"), Expr::from(15.0)),
-        (Ident::from("height"), Ident::from("height").into()),
-        (Ident::from("status"), Expr::Symbol("online".to_string())),
+        (Ident::from("name").into(), Expr::from("bob")),
+        (Ident::from("age").into(), Expr::from(15)),
+        (Ident::from("height").into(), Ident::from("height").into()),
+        (
+            Ident::from("status").into(),
+            Expr::Symbol("online".to_string()),
+        ),
+    ])
+    .into();
+    test_output(input, expected)
+}
+
+#[test]
+fn test_slice_expression() {
+    let input = "a[1..n - 1]";
+    let expected = Expr::Slice {
+        object: Expr::Ident(Ident::from("a")).into(),
+        start: Expr::Integer(1).into(),
+        end: Expr::Infix(
+            Expr::Ident(Ident::from("n")).into(),
+            String::from("-"),
+            Expr::Integer(1).into(),
+        )
+        .into(),
+    }
+    .into();
+    test_output(input, vec![expected])
+}
+
+#[test]
+fn test_hash_literal_keys() {
+    let input = "{ 1 = 'a', :ok = true, 'b' = 2 }";
+    let expected = Expr::Hash(vec![
+        (Expr::Integer(1), Expr::from("a")),
+        (Expr::Symbol("ok".to_string()), Expr::Boolean(true)),
+        (Expr::from("b"), Expr::Integer(2)),
     ])
     .into();
     test_output(input, expected)
@@ -196,7 +521,7 @@ fn test_nested() {
         ("[[]]", Expr::Array(vec![Expr::Array(vec![])]).into()),
         (
             "{ a = {} }",
-            Expr::Hash(vec![(Ident::from("a"), Expr::Hash(vec![]))]).into(),
+            Expr::Hash(vec![(Ident::from("a").into(), Expr::Hash(vec![]))]).into(),
         ),
     ];
     test_multiple(cases)
@@ -206,7 +531,7 @@ fn test_nested() {
 fn test_call_expression() {
     let input = "foobar(a, b, c)";
     let expected = Expr::Call {
-        function: Box::new(Ident::from("foobar").into()),
+        function: Expr::from(Ident::from("foobar")).into(),
         arguments: vec![
             Ident::from("a").into(),
             Ident::from("b").into(),
@@ -217,25 +542,98 @@ fn test_call_expression() {
     test_output(input, expected)
 }
 
+fn call(function: &str, arguments: Vec<Expr>) -> Expr {
+    Expr::Call {
+        function: Expr::from(Ident::from(function)).into(),
+        arguments,
+    }
+}
+
+#[test]
+fn test_pipe_expression() {
+    let cases = vec![
+        // Piped into the first argument by default
+        (
+            "xs |> join(', ')",
+            call("join", vec![Ident::from("xs").into(), Expr::from(", ")]).into(),
+        ),
+        // Bare functions are called with the piped value
+        (
+            "xs |> len",
+            call("len", vec![Ident::from("xs").into()]).into(),
+        ),
+        // Pipes chain from left to right
+        (
+            "1 + 2 |> f |> g(3)",
+            call(
+                "g",
+                vec![
+                    call(
+                        "f",
+                        vec![Expr::Infix(
+                            Expr::Integer(1).into(),
+                            String::from("+"),
+                            Expr::Integer(2).into(),
+                        )],
+                    ),
+                    Expr::Integer(3),
+                ],
+            )
+            .into(),
+        ),
+    ];
+    test_multiple(cases)
+}
+
+#[test]
+fn test_pipe_placeholder() {
+    let cases = vec![
+        (
+            "', ' |> join(xs, _)",
+            call("join", vec![Ident::from("xs").into(), Expr::from(", ")]).into(),
+        ),
+        (
+            "x |> f(a, _, b)",
+            call(
+                "f",
+                vec![
+                    Ident::from("a").into(),
+                    Ident::from("x").into(),
+                    Ident::from("b").into(),
+                ],
+            )
+            .into(),
+        ),
+        (
+            "x |> f(_, a)",
+            call("f", vec![Ident::from("x").into(), Ident::from("a").into()]).into(),
+        ),
+    ];
+    test_multiple(cases)
+}
+
 #[test]
 fn test_index_expression() {
     let input = "1..5[2] == { a = 3 }.a";
     let expected = Expr::Infix(
-        Box::new(Expr::Member {
-            property: Box::new(Expr::Number(2.0)),
-            object: Box::new(Expr::Infix(
-                Box::new(Expr::Number(1.0)),
+        Expr::Member {
+            property: Expr::Integer(2).into(),
+            object: Expr::Infix(
+                Expr::Integer(1).into(),
                 String::from(".."),
-                Box::new(Expr::Number(5.0)),
-            )),
+                Expr::Integer(5).into(),
+            )
+            .into(),
             computed: true,
-        }),
+        }
+        .into(),
         "==".to_string(),
-        Box::new(Expr::Member {
+        Expr::Member {
             property: Expr::Ident(Ident::from("a")).into(),
-            object: Expr::Hash(vec![(Ident::from("a"), Expr::Number(3.0))]).into(),
+            object: Expr::Hash(vec![(Ident::from("a").into(), Expr::Integer(3))]).into(),
             computed: false,
-        }),
+        }
+        .into(),
     )
     .into();
     test_output(input, expected)
@@ -243,7 +641,7 @@ fn test_index_expression() {
 
 #[test]
 fn test_pattern() {
-    let input = "[ 4.5, foo, true, :bar, 'hello', { abc, def }, _ ]";
+    let input = "[ 4.5, foo, true, :bar, 'hello', { abc, def }, _, 0..10 ]";
     let expected = Pattern::Array(vec![
         Pattern::Number(4.5),
         Pattern::Ident(Ident::from("foo")),
@@ -252,6 +650,7 @@ fn test_pattern() {
         Pattern::String("hello".to_string()),
         Pattern::Hash(vec![(Ident::from("abc"), None), (Ident::from("def"), None)]),
         Pattern::Nothing,
+        Pattern::Range(0.0, 10.0),
     ]);
 
     // Specialized check
@@ -270,6 +669,71 @@ fn test_pattern() {
     test_output(input, expected)
 }
 
+#[test]
+fn test_match_guard() {
+    let input = "x :: { n if n > 0 -> n, _ -> 0 }";
+    let expected = Expr::Match {
+        condition: Expr::from(Ident::from("x")).into(),
+        cases: vec![
+            (
+                Pattern::Ident(Ident::from("n")),
+                Some(Expr::Infix(
+                    Expr::from(Ident::from("n")).into(),
+                    String::from(">"),
+                    Expr::from(0).into(),
+                )),
+                Expr::Ident(Ident::from("n")).into(),
+            ),
+            (Pattern::Nothing, None, Expr::from(0).into()),
+        ],
+    }
+    .into();
+    test_output(input, vec![expected]);
+}
+
+#[test]
+fn test_hash_pattern_alias() {
+    let input = "let { name: n, age, email:e } = person";
+    let expected = vec![Stmt::Assign(
+        Pattern::Hash(vec![
+            (Ident::from("name"), Some(Ident::from("n"))),
+            (Ident::from("age"), None),
+            (Ident::from("email"), Some(Ident::from("e"))),
+        ]),
+        Ident::from("person").into(),
+    )];
+    test_output(input, expected)
+}
+
+#[test]
+fn test_rest_pattern() {
+    let input = "let [head, ...tail] = [1, 2, 3]";
+    let expected = vec![Stmt::Assign(
+        Pattern::Array(vec![
+            Pattern::Ident(Ident::from("head")),
+            Pattern::Rest(Ident::from("tail")),
+        ]),
+        Expr::Array(vec![Expr::Integer(1), Expr::Integer(2), Expr::Integer(3)]),
+    )];
+    test_output(input, expected)
+}
+
+#[test]
+fn test_rest_pattern_must_be_last() {
+    for input in &["let [...a, b] = c", "let [a, ...b, ...c] = d"] {
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l, input.to_string());
+        let errors = match p.parse_program() {
+            Ok(program) => panic!("Expected errors, got {:?}", program),
+            Err(errors) => errors,
+        };
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnsupportedToken(TokenType::Spread)
+        );
+    }
+}
+
 #[test]
 fn test_match_expression() {
     let input = "true :: {
@@ -281,19 +745,21 @@ fn test_match_expression() {
         cases: vec![
             (
                 Pattern::Boolean(true),
+                None,
                 Expr::Infix(
-                    Box::new(Expr::from(1.0)),
+                    Expr::from(1).into(),
                     String::from("+"),
-                    Box::new(Expr::from(1.0)),
+                    Expr::from(1).into(),
                 )
                 .into(),
             ),
             (
                 Pattern::Boolean(false),
+                None,
                 Expr::Infix(
-                    Box::new(Expr::from(2.0)),
+                    Expr::from(2).into(),
                     String::from("+"),
-                    Box::new(Expr::from(2.0)),
+                    Expr::from(2).into(),
                 )
                 .into(),
             ),
@@ -303,14 +769,176 @@ fn test_match_expression() {
     test_output(input, expected);
 }
 
+#[test]
+fn test_multiple_errors() {
+    let input = "let x 5; let y = ; let z = 3; z";
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    let errors = match p.parse_program() {
+        Ok(program) => panic!("Expected errors, got {:?}", program),
+        Err(errors) => errors,
+    };
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::ExpectedFound {
+            expected: TokenType::Assign,
+            found: TokenType::Integer(5),
+        }
+    );
+    assert_eq!(
+        errors[1].kind,
+        ParseErrorKind::NoPrefixFound(TokenType::Semicolon)
+    );
+}
+
+#[test]
+fn test_recovers_at_keywords() {
+    let input = "let a = )\nlet b = 2\nreturn (";
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    match p.parse_program() {
+        Ok(program) => panic!("Expected errors, got {:?}", program),
+        Err(errors) => assert_eq!(errors.len(), 2),
+    }
+}
+
+#[test]
+fn test_recovers_after_blocks() {
+    // The rest of the block is skipped, instead of its `}` being reported on its own
+    let input = "let f = fn (x) -> {\n let a = )\n let b = 2\n b\n}\nlet c = ;";
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::NoPrefixFound(TokenType::RightParen)
+    );
+    assert_eq!(
+        errors[1].kind,
+        ParseErrorKind::NoPrefixFound(TokenType::Semicolon)
+    );
+
+    let input = "let f = fn (x) -> {\n let a = )\n let b = 2\n b\n}";
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    assert_eq!(p.parse_program().unwrap_err().len(), 1);
+}
+
+#[test]
+fn test_spans() {
+    let input = "1 + 2";
+    let tokens: Vec<Token> = {
+        let mut l = Lexer::new(input);
+        (0..3).map(|_| l.next_token()).collect()
+    };
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    let program = p.parse_program().unwrap();
+
+    let infix = match &program.0[0] {
+        Stmt::Expr(expr) => expr,
+        stmt => panic!("Expected an expression, got {}", stmt),
+    };
+    // The infix node covers both operands
+    assert_eq!(
+        infix.span,
+        Some(tokens[0].position.start..tokens[2].position.end)
+    );
+    match &infix.node {
+        Expr::Infix(left, _, right) => {
+            assert_eq!(left.span, Some(tokens[0].position.clone()));
+            assert_eq!(right.span, Some(tokens[2].position.clone()));
+        }
+        expr => panic!("Expected an infix expression, got {}", expr),
+    }
+}
+
+#[test]
+fn test_trailing_commas() {
+    // Each input should parse the same as it would without the trailing comma
+    let cases = [
+        ("[1, 2, 3,]", "[1, 2, 3]"),
+        ("{ a = 1, b, }", "{ a = 1, b }"),
+        ("f(a, b,)", "f(a, b)"),
+        ("fn (a, b = 1,) -> a", "fn (a, b = 1) -> a"),
+        ("let [a, b,] = c", "let [a, b] = c"),
+        ("let { a, b: c, } = d", "let { a, b: c } = d"),
+        ("x :: { 1 -> 2, _ -> 3, }", "x :: { 1 -> 2, _ -> 3 }"),
+    ];
+    test_equivalent(&cases);
+
+    // A lone comma still isn't an item
+    let input = "[,]";
+    let mut p = Parser::new(Lexer::new(input), input.to_string());
+    assert!(p.parse_program().is_err());
+}
+
+#[test]
+fn test_bitwise_precedence() {
+    let input = "6 & 3";
+    let expected = vec![Expr::Infix(
+        Expr::Integer(6).into(),
+        String::from("&"),
+        Expr::Integer(3).into(),
+    )
+    .into()];
+    test_output(input, expected);
+
+    // Shifts bind tightest, then &, ^, and |, all of them tighter than comparisons
+    let cases = [
+        ("1 << 4 + 1", "1 << (4 + 1)"),
+        ("a | b ^ c & d << 1", "a | (b ^ (c & (d << 1)))"),
+        ("a & b == c", "(a & b) == c"),
+        ("a | b < c", "(a | b) < c"),
+        ("a >> 1 >> 2", "(a >> 1) >> 2"),
+    ];
+    test_equivalent(&cases);
+}
+
+#[test]
+fn test_power_precedence() {
+    // Powers bind tighter than products and negation, and group from the right
+    let cases = [
+        ("2 * 3 ** 2", "2 * (3 ** 2)"),
+        ("2 ** 3 ** 2", "2 ** (3 ** 2)"),
+        ("-2 ** 2", "-(2 ** 2)"),
+        ("-a * b", "(-a) * b"),
+        ("2 ** -3", "2 ** (-3)"),
+        ("a ** 2 % 3", "(a ** 2) % 3"),
+    ];
+    test_equivalent(&cases);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let input = "
+    import http from 'http'
+    let [a, { b }] = [1, { b = :two, 3 = true }]
+    let f = fn (x, y) -> x :: {
+        0 -> -y,
+        [_, 'z'] -> { return y[0].c },
+        _ -> if x >= 1 { x + 1.5 } else { f(x - 1, null) }
+    }
+    ";
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    let program = p.parse_program().unwrap();
+
+    let json = serde_json::to_string(&program).unwrap();
+    let deserialized: Program = serde_json::from_str(&json).unwrap();
+    assert_eq!(program, deserialized);
+}
+
 fn test_output(input: &str, expected: Vec<Stmt>) {
     let l = Lexer::new(input);
     let mut p = Parser::new(l, input.to_string());
     let program = p.parse_program();
     if let Ok(program) = program {
         check_program(program, expected);
-    } else if let Err(err) = program {
-        println!("Parser had an error:\n{}", err);
+    } else if let Err(errors) = program {
+        for err in errors {
+            println!("Parser had an error:\n{}", err);
+        }
         panic!("Parser errored");
     }
 }
@@ -321,6 +949,15 @@ fn test_multiple(cases: Vec<(&str, Vec<Stmt>)>) {
     }
 }
 
+// Checks that each input parses to the same statements as its equivalent
+fn test_equivalent(cases: &[(&str, &str)]) {
+    for (input, equivalent) in cases {
+        let mut p = Parser::new(Lexer::new(equivalent), equivalent.to_string());
+        let expected = p.parse_program().unwrap().0;
+        test_output(input, expected);
+    }
+}
+
 fn check_program(program: Program, expected: Vec<Stmt>) {
     expected.iter().enumerate().for_each(|(index, stmt)| {
         assert_eq!(stmt, &program.0[index]);
@@ -1,21 +1,35 @@
 use super::{context::Context, util};
-use crate::ast::{Expr, Program, Stmt, Pattern};
-use crate::style::{bold, yellow};
-use std::default::Default;
+use crate::ast::{Expr, Pattern, Program, Stmt};
+use crate::style::{bold, emphasize, yellow};
+
+#[cfg(test)]
+#[path = "./analyze_test.rs"]
+mod analyze_test;
 
 type AnalysisResult = Result<(), Vec<String>>;
 
 pub fn analyze_stmts(program: Program, parent: Option<&mut Context>) -> AnalysisResult {
-    let mut default = Context {
-        ..Default::default()
-    };
-    let mut context = match parent {
+    let mut default = Context::new_global();
+    let context = match parent {
         Some(ctx) => ctx,
         None => &mut default,
     };
+    let analysis = analyze_block(program, context);
+    for name in context.take_unread() {
+        warn_unused(&name, context);
+    }
+    analysis
+}
+
+// Analyzes statements that run in `context` itself, rather than in a block of their own
+fn analyze_block(program: Program, context: &mut Context) -> AnalysisResult {
     let mut results = vec![];
+    context.hoist(program.0.iter().flat_map(|stmt| match stmt {
+        Stmt::Assign(pattern, _) => pattern.bindings().into_iter().map(|name| name.0).collect(),
+        _ => vec![],
+    }));
     for stmt in program.0 {
-        let analysis = analyze_stmt(stmt, &mut context);
+        let analysis = analyze_stmt(stmt, context);
         if let Err(errors) = analysis {
             for err in errors {
                 results.push(err)
@@ -30,32 +44,10 @@ pub fn analyze_stmts(program: Program, parent: Option<&mut Context>) -> Analysis
 
 pub fn analyze_stmt(stmt: Stmt, context: &mut Context) -> AnalysisResult {
     match stmt {
-        Stmt::Expr(expr) => analyze_expr(expr, context),
+        Stmt::Expr(expr) => analyze_expr(expr.node, context),
         Stmt::Assign(name, expr) => {
             let mut errors = vec![];
-            if let Pattern::Array(array) = name {
-                for item in array {
-                    if let Pattern::Ident(item) = item {
-                        context.add(item.0, expr.clone());
-                    } else {
-                        errors.push(
-                            "Attempted to pattern match with non identifier value".to_string(),
-                        )
-                    }
-                }
-            } else if let Pattern::Hash(hash) = name {
-                for (_key, value) in hash {
-                    if let Pattern::Ident(value) = value {
-                        context.add(value.0, expr.clone());
-                    } else {
-                        errors.push(
-                            "Attempted to pattern match with non identifier value".to_string(),
-                        )
-                    }
-                }
-            } else if let Pattern::Ident(ident) = name {
-                context.add(ident.0, expr.clone());
-            }
+            add_pattern(name, &expr, context, &mut errors);
             let res = analyze_expr(expr, context);
             interpolate_errors(res, &mut errors);
             if !errors.is_empty() {
@@ -63,10 +55,39 @@ pub fn analyze_stmt(stmt: Stmt, context: &mut Context) -> AnalysisResult {
             }
             Ok(())
         }
-        Stmt::Return(_) => Ok(()),
-        Stmt::Import { name, source } => {
+        Stmt::Return(expr) => analyze_expr(expr, context),
+        Stmt::Break | Stmt::Continue if !context.in_loop => Err(vec![format!(
+            "{} can only be used inside a loop",
+            bold(&yellow(&stmt.to_string()))
+        )]),
+        Stmt::Break | Stmt::Continue => Ok(()),
+        Stmt::Import {
+            name,
+            source,
+            alias,
+        } => {
             let mut errors = vec![];
-            if !util::is_ident(&name) {
+            // With an alias, the import is only bound under the alias
+            match (&name, &alias) {
+                (Pattern::Ident(_), Some(Pattern::Ident(alias))) => {
+                    context.add(alias.0.clone(), source.clone())
+                }
+                (Pattern::Ident(ident), None) => context.add(ident.0.clone(), source.clone()),
+                _ => {}
+            }
+            if let Some(alias) = alias
+                .as_ref()
+                .filter(|alias| !matches!(alias, Pattern::Ident(_)))
+            {
+                errors.push(
+          format!("While analyzing an import statement, we were expecting to find the alias as an identifier, but we instead found this: {}
+
+Hint: Only use identifiers as aliases in imports
+Here is an example of a valid aliased import statement:
+import ident from 'string' as other;", alias)
+        )
+            }
+            if !matches!(name, Pattern::Ident(_)) {
                 errors.push(
           format!("While analyzing an import statement, we were expecting to find the name as an identifier, but we instead found this: {}
           
@@ -93,44 +114,118 @@ Hint: Imports in bliss as static, meaning that you can't use other forms of expr
 pub fn analyze_expr(expr: Expr, context: &mut Context) -> AnalysisResult {
     let mut errors = vec![];
     match expr {
-        Expr::Ident(ident) => {
-            if !context.has(ident.0.clone()) {
-                errors.push(format!(
-                    "Identifier {} used before declaration",
-                    bold(&yellow(&ident.0))
-                ))
-            }
-        }
+        Expr::Ident(ident) if !context.read(&ident.0) && !context.read_hoisted(&ident.0) => errors
+            .push(format!(
+                "Identifier {} used before declaration",
+                bold(&yellow(&ident.0))
+            )),
         Expr::If {
             condition,
             consequence,
             alternative,
         } => {
-            let res = analyze_expr(*condition, context);
+            let res = analyze_expr(condition.node, context);
             interpolate_errors(res, &mut errors);
             let res = analyze_stmts(consequence, Some(&mut Context::new_child_block(context)));
             interpolate_errors(res, &mut errors);
             let res = analyze_stmts(alternative, Some(&mut Context::new_child_block(context)));
             interpolate_errors(res, &mut errors);
         }
-        // Expr::Call {
-        //     function,
-        //     arguments,
-        // } => {
-        //     if util::is_function(&*function) || util::is_ident(&*function) {
-        //         let res =
-        //             analyze_expr(*function.clone(), &mut Context::new_function_block(context));
-        //         interpolate_errors(res, &mut errors);
-        //     } else {
-        //         errors.push(
-        //             format!("While parsing a call expression, we expected a function literal or an identifier, but we found {}, which is not callable", function)
-        //         )
-        //     }
-        //     for arg in arguments {
-        //         let res = analyze_expr(arg, context);
-        //         interpolate_errors(res, &mut errors);
-        //     }
-        // }
+        Expr::Assign { name, value } => {
+            if !context.has(name.0.clone()) && !context.read_hoisted(&name.0) {
+                errors.push(format!(
+                    "Identifier {} assigned before declaration",
+                    bold(&yellow(&name.0))
+                ));
+            }
+            let res = analyze_expr(value.node, context);
+            interpolate_errors(res, &mut errors);
+        }
+        Expr::AssignMember { target, value } => {
+            let res = analyze_expr(target.node, context);
+            interpolate_errors(res, &mut errors);
+            let res = analyze_expr(value.node, context);
+            interpolate_errors(res, &mut errors);
+        }
+        Expr::While { condition, body } => {
+            let res = analyze_expr(condition.node, context);
+            interpolate_errors(res, &mut errors);
+            // The body runs in the surrounding scope, so its `let`s are still bound after the loop
+            let in_loop = std::mem::replace(&mut context.in_loop, true);
+            let res = analyze_block(body, context);
+            context.in_loop = in_loop;
+            interpolate_errors(res, &mut errors);
+        }
+        Expr::Call {
+            function,
+            arguments,
+        } => {
+            if util::is_callable(&function) {
+                let res = analyze_expr(function.node, context);
+                interpolate_errors(res, &mut errors);
+            } else {
+                errors.push(
+                    format!("While analyzing a call expression, we expected a function literal or an identifier, but we found {}, which is not callable", function)
+                )
+            }
+            for arg in arguments {
+                let res = analyze_expr(arg, context);
+                interpolate_errors(res, &mut errors);
+            }
+        }
+        Expr::Prefix(_, right) => {
+            let res = analyze_expr(right.node, context);
+            interpolate_errors(res, &mut errors);
+        }
+        Expr::Infix(left, _, right) => {
+            let res = analyze_expr(left.node, context);
+            interpolate_errors(res, &mut errors);
+            let res = analyze_expr(right.node, context);
+            interpolate_errors(res, &mut errors);
+        }
+        Expr::Member {
+            property,
+            object,
+            computed,
+        } => {
+            let res = analyze_expr(object.node, context);
+            interpolate_errors(res, &mut errors);
+            // `foo.bar` is a key lookup, not a variable
+            if computed {
+                let res = analyze_expr(property.node, context);
+                interpolate_errors(res, &mut errors);
+            }
+        }
+        Expr::Slice { object, start, end } => {
+            for expr in [object, start, end] {
+                let res = analyze_expr(expr.node, context);
+                interpolate_errors(res, &mut errors);
+            }
+        }
+        Expr::Match { condition, cases } => {
+            let res = analyze_expr(condition.node.clone(), context);
+            interpolate_errors(res, &mut errors);
+            for (pattern, guard, body) in cases {
+                if !util::can_match(&pattern, &condition.node) {
+                    context.warn(format!(
+                        "The pattern {} can never match {}, so its arm is never used",
+                        emphasize(&pattern.to_string()),
+                        condition.node
+                    ));
+                }
+                // The pattern's bindings are only visible in its own arm
+                let mut arm = Context::new_child_block(context);
+                for name in pattern.bindings() {
+                    arm.add(name.0.clone(), Expr::Symbol(name.0));
+                }
+                if let Some(guard) = guard {
+                    let res = analyze_expr(guard, &mut arm);
+                    interpolate_errors(res, &mut errors);
+                }
+                let res = analyze_stmts(body, Some(&mut arm));
+                interpolate_errors(res, &mut errors);
+            }
+        }
         Expr::Array(items) => {
             for item in items {
                 let res = analyze_expr(item, context);
@@ -138,15 +233,31 @@ pub fn analyze_expr(expr: Expr, context: &mut Context) -> AnalysisResult {
             }
         }
         Expr::Hash(items) => {
-            for (_key, value) in items {
+            for (key, value) in items {
+                // Identifier keys are literal, but other keys are evaluated
+                if !util::is_ident(&key) {
+                    let res = analyze_expr(key, context);
+                    interpolate_errors(res, &mut errors);
+                }
                 let res = analyze_expr(value, context);
                 interpolate_errors(res, &mut errors);
             }
         }
-        Expr::Function { body, parameters } => {
+        Expr::Function {
+            body,
+            parameters,
+            rest,
+        } => {
+            // Defaults are evaluated where the function is defined, before any params exist
+            for (_, default) in parameters.iter() {
+                if let Some(default) = default {
+                    let res = analyze_expr(default.clone(), context);
+                    interpolate_errors(res, &mut errors);
+                }
+            }
             let mut context = Context::new_function_block(context);
             // Make sure params are defined before checking the function
-            for param in parameters {
+            for param in parameters.into_iter().map(|(param, _)| param).chain(rest) {
                 context.add(param.0.clone(), Expr::Symbol(param.0));
             }
             let res = analyze_stmts(body, Some(&mut context));
@@ -161,6 +272,44 @@ pub fn analyze_expr(expr: Expr, context: &mut Context) -> AnalysisResult {
     Ok(())
 }
 
+// Adds every name a destructuring pattern binds
+fn add_pattern(pattern: Pattern, expr: &Expr, context: &mut Context, errors: &mut Vec<String>) {
+    match pattern {
+        Pattern::Ident(ident) | Pattern::Rest(ident) => declare(ident.0, expr, context),
+        Pattern::Array(items) => {
+            for item in items {
+                add_pattern(item, expr, context, errors);
+            }
+        }
+        Pattern::Hash(items) => {
+            for (key, alias) in items {
+                declare(alias.unwrap_or(key).0, expr, context);
+            }
+        }
+        Pattern::Nothing => {}
+        _ => errors.push("Attempted to pattern match with non identifier value".to_string()),
+    }
+}
+
+// Binds a name from a `let`, warning if it hides one from earlier in the same block
+// Names starting with `_` are allowed to go unused
+fn declare(name: String, expr: &Expr, context: &mut Context) {
+    if context.locals.contains_key(&name) {
+        context.warn(format!(
+            "{} was already declared in this block, so this declaration shadows it",
+            emphasize(&name)
+        ));
+    }
+    if !name.starts_with('_') && !context.add_unread(name.clone()) {
+        warn_unused(&name, context);
+    }
+    context.add(name, expr.clone());
+}
+
+fn warn_unused(name: &str, context: &Context) {
+    context.warn(format!("{} is declared but never used", emphasize(name)));
+}
+
 fn interpolate_errors(res: AnalysisResult, errors: &mut Vec<String>) {
     if let Err(errs) = res {
         for err in errs {
@@ -0,0 +1,207 @@
+use super::*;
+use crate::evaluation::{config::EvaluatorConfig, env::Environment, error::EvalError, Evaluator};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn parse(input: &str) -> Program {
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l, input.to_string());
+    match p.parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for err in errors {
+                println!("Parser had an error:\n{}", err);
+            }
+            panic!("Parser errored");
+        }
+    }
+}
+
+fn test_errors(input: &str, expected: usize) -> Vec<String> {
+    match analyze_stmts(parse(input), None) {
+        Ok(()) => {
+            assert_eq!(expected, 0, "Expected {} to have errors", input);
+            vec![]
+        }
+        Err(errors) => {
+            assert_eq!(errors.len(), expected, "{:?}", errors);
+            errors
+        }
+    }
+}
+
+fn test_warnings(input: &str) -> Vec<String> {
+    let mut context = Context::new_global();
+    analyze_stmts(parse(input), Some(&mut context)).expect("analysis failed");
+    context.take_warnings()
+}
+
+#[test]
+fn test_shadow_warnings() {
+    let shadows = |input: &str| -> Vec<String> {
+        test_warnings(input)
+            .into_iter()
+            .filter(|warning| warning.contains("shadows"))
+            .collect()
+    };
+    let warnings = shadows("let x = 1; let x = 2; x");
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert!(warnings[0].contains("x"), "{}", warnings[0]);
+    assert_eq!(
+        shadows("let [a, { b }] = [1, { b = 2 }]; let { a } = { a = 3 }").len(),
+        1
+    );
+
+    // Shadowing something from an outer block, or a builtin, is fine
+    assert!(shadows("let x = 1; if true { let x = 2 } else {}").is_empty());
+    assert!(shadows("let x = 1; let f = fn x -> { let y = x }").is_empty());
+    assert!(shadows("let len = 1").is_empty());
+}
+
+#[test]
+fn test_unused_warnings() {
+    let warnings = test_warnings("let x = 1; let y = 2; y");
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert!(warnings[0].contains("x"), "{}", warnings[0]);
+    assert!(warnings[0].contains("never used"), "{}", warnings[0]);
+
+    // Reads from inner blocks and functions count
+    assert!(test_warnings("let x = 1; let f = fn () -> x; f()").is_empty());
+    assert!(test_warnings("let x = 1; if true { log(x) } else {}").is_empty());
+    // Parameters and names starting with `_` are exempt
+    assert!(test_warnings("let f = fn (a, b) -> a; f(1, 2)").is_empty());
+    assert!(test_warnings("let _x = 1; let [_, _y] = [1, 2]").is_empty());
+    // Inner blocks are checked too
+    assert_eq!(test_warnings("if true { let z = 1 } else {}").len(), 1);
+    // A binding that gets shadowed before it's read is unused
+    assert_eq!(test_warnings("let x = 1; let x = 2; x").len(), 2);
+}
+
+#[test]
+fn test_break_outside_loop() {
+    test_errors("while true { break; continue }", 0);
+    test_errors("while true { if true { break } else { continue } }", 0);
+    let errors = test_errors("break", 1);
+    assert!(
+        errors[0].contains("only be used inside a loop"),
+        "{}",
+        errors[0]
+    );
+    test_errors("if true { continue } else {}", 1);
+    // Functions don't see the loop they're defined in
+    test_errors("while true { let f = fn () -> { break }; f() }", 1);
+    // The loop ends with the body, even though its bindings don't
+    test_errors("while true { break }; break", 1);
+}
+
+#[test]
+fn test_while_scope() {
+    // Loop bodies run in the surrounding scope, so their bindings are still there afterwards
+    test_errors("let i = 0; while i < 3 { let y = i; i += 1 }; y", 0);
+}
+
+#[test]
+fn test_later_declarations_in_functions() {
+    // Functions look names up when they're called, so they can use ones declared after them
+    let mutual = "let is_even = fn n -> if n == 0 { true } else { is_odd(n - 1) }
+let is_odd = fn n -> if n == 0 { false } else { is_even(n - 1) }
+is_even(4)";
+    test_errors(mutual, 0);
+    assert!(test_warnings(mutual).is_empty());
+    test_errors("let f = fn () -> g(); let g = fn () -> 1; f()", 0);
+    test_errors("let f = fn () -> { total = 1 }; let total = 0; f()", 0);
+    test_errors(
+        "if true { let f = fn () -> g(); let g = 1; f() } else {}",
+        0,
+    );
+
+    // Outside of a function, or later in the function itself, the name doesn't exist yet
+    test_errors("g(); let g = fn () -> 1", 1);
+    test_errors("let f = fn () -> { y; let y = 1 }; f()", 1);
+    test_errors("let f = fn () -> missing; f()", 1);
+}
+
+#[test]
+fn test_match_arms() {
+    test_errors(
+        "let x = 1; x :: { 1 -> x, n if n > 0 -> n, [a, ...rest] -> rest, _ -> 0 }",
+        0,
+    );
+    let errors = test_errors("1 :: { 1 -> missing, _ -> 0 }", 1);
+    assert!(errors[0].contains("missing"), "{}", errors[0]);
+    test_errors("1 :: { n if missing -> n, _ -> 0 }", 1);
+    test_errors("oops :: { _ -> 0 }", 1);
+    // A pattern's bindings don't leak into other arms or after the match
+    test_errors("1 :: { n -> n, _ -> n }", 1);
+    test_errors("let x = 1 :: { n -> n }; n", 1);
+
+    // Names used only inside an arm count as read
+    assert!(test_warnings("let y = 2; 1 :: { _ -> y }").is_empty());
+
+    let warnings = test_warnings("1 :: { 'one' -> 1, :two -> 2, 0..5 -> 3, _ -> 4 }");
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].contains("'one'"), "{}", warnings[0]);
+    assert!(warnings[0].contains("can never match"), "{}", warnings[0]);
+    assert!(test_warnings("let x = 1; x :: { 'one' -> 1, _ -> 2 }").is_empty());
+}
+
+#[test]
+fn test_defined_identifiers() {
+    test_errors("let x = 5; x + 1", 0);
+    test_errors(
+        "let [a, _, { b, c }] = [1, 2, { b = 3, c = 4 }]; a + b + c",
+        0,
+    );
+    test_errors("let f = fn (a, b) -> a + b; f(1, 2)", 0);
+    test_errors("let fib = fn n -> if n < 2 { n } else { fib(n - 1) }", 0);
+}
+
+#[test]
+fn test_builtins_are_defined() {
+    test_errors("log(len([1, 2]))", 0);
+}
+
+#[test]
+fn test_undefined_identifiers() {
+    test_errors("x", 1);
+    test_errors("let f = fn a -> a + b", 1);
+    test_errors("if y { 1 } else { z }", 2);
+    test_errors("-a + b[c]", 3);
+    test_errors("x = 1", 1);
+    test_errors("let x = 1; x = x + 1", 0);
+}
+
+#[test]
+fn test_import_alias() {
+    test_errors("import foo from 'foo'; foo", 0);
+    test_errors("import foo from 'foo' as bar; bar", 0);
+    // The alias replaces the original name
+    test_errors("import foo from 'foo' as bar; foo", 1);
+    let errors = test_errors("import foo from 'foo' as [bar]", 1);
+    assert!(errors[0].contains("alias as an identifier"));
+}
+
+#[test]
+fn test_not_callable() {
+    let errors = test_errors("5(1)", 1);
+    assert!(errors[0].contains("not callable"));
+}
+
+#[test]
+fn test_semantic_error_before_runtime() {
+    let input = "log(undefinedVar)";
+    let errors = test_errors(input, 1);
+    assert!(errors[0].contains("undefinedVar"));
+    assert!(errors[0].contains("used before declaration"));
+
+    // Without analysis, this would only surface when evaluating
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let result = Evaluator::new(env, EvaluatorConfig::default()).eval_program(parse(input));
+    assert_eq!(
+        result,
+        Err(EvalError::UndefinedIdentifier("undefinedVar".to_string()))
+    );
+}
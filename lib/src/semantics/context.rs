@@ -1,31 +1,49 @@
-use std::{collections::HashMap, default::Default};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 use crate::ast::Expr;
+use crate::evaluation::builtins;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Context {
     // Parent context
     pub parent: Option<Box<Context>>,
     pub in_function: bool,
+    // Whether this is the outermost block of a function's body
+    pub function_body: bool,
+    // Whether `break` and `continue` have a loop to apply to
+    pub in_loop: bool,
     // All the declared variables in a context
     pub locals: HashMap<String, Expr>,
+    // Names bound by `let` in this context that haven't been read yet
+    // Shared with copies of this context, so reads from child contexts count
+    unread: Rc<RefCell<HashSet<String>>>,
+    // Every name a `let` in this block binds, including ones that come later
+    hoisted: HashSet<String>,
+    // Hoisted names read by a function before their `let` was reached
+    // These are read once the function is called, so their bindings count as used
+    read_early: Rc<RefCell<HashSet<String>>>,
+    // Problems that don't stop the program from running, shared with every child context
+    warnings: Rc<RefCell<Vec<String>>>,
 }
 
-impl Default for Context {
-    fn default() -> Self {
-        Self {
-            parent: None,
-            in_function: false,
-            locals: HashMap::new(),
+impl Context {
+    // The top level context
+    // Builtins live in a parent of it, so a top level `let` shadowing one isn't in the same block
+    pub fn new_global() -> Context {
+        let mut builtins_context = Context::default();
+        for name in builtins::get_builtins().keys() {
+            builtins_context.add(name.clone(), Expr::Symbol(name.clone()));
         }
+        builtins_context.new_child_block()
     }
-}
-
-impl Context {
     pub fn new_child_block(&self) -> Context {
         Context {
             parent: Some(Box::new(self.clone())),
             in_function: self.in_function,
+            in_loop: self.in_loop,
+            warnings: self.warnings.clone(),
             ..Default::default()
         }
     }
@@ -33,28 +51,82 @@ impl Context {
         Context {
             parent: Some(Box::new(self.clone())),
             in_function: true,
+            function_body: true,
+            warnings: self.warnings.clone(),
             ..Default::default()
         }
     }
 
+    pub fn warn(&self, warning: String) {
+        self.warnings.borrow_mut().push(warning);
+    }
+    // Gives back every warning found so far, from this context or any of its children
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
     pub fn add(&mut self, name: String, expr: Expr) {
         // Add a new item to the vec
         self.locals.insert(name, expr);
     }
 
+    // Tracks a `let` binding, so it can be reported if it's never read
+    // Returns false if an earlier binding of the name in this context was never read
+    pub fn add_unread(&mut self, name: String) -> bool {
+        if self.read_early.borrow().contains(&name) {
+            return true;
+        }
+        self.unread.borrow_mut().insert(name)
+    }
+    // Records the names this block's `let`s will bind, before any of it is analyzed
+    pub fn hoist(&mut self, names: impl IntoIterator<Item = String>) {
+        self.hoisted.extend(names);
+    }
+    // Whether a function can use a name that's declared later in a block around it
+    // Functions only look names up when they're called, so this is fine outside of the function
+    // itself, but not inside it, where the `let` would run after the name is used
+    pub fn read_hoisted(&self, name: &str) -> bool {
+        let mut context = self;
+        let mut outside_function = false;
+        loop {
+            if outside_function && context.hoisted.contains(name) {
+                context.read_early.borrow_mut().insert(name.to_string());
+                return true;
+            }
+            outside_function = outside_function || context.function_body;
+            match &context.parent {
+                Some(parent) => context = parent,
+                None => return false,
+            }
+        }
+    }
+    // Marks the closest binding of a name as used, returning whether there is one
+    pub fn read(&self, name: &str) -> bool {
+        if self.locals.contains_key(name) {
+            self.unread.borrow_mut().remove(name);
+            return true;
+        }
+        match &self.parent {
+            Some(parent) => parent.read(name),
+            None => false,
+        }
+    }
+    // Gives back the names in this context that were never read, in order
+    pub fn take_unread(&self) -> Vec<String> {
+        let mut unread: Vec<String> = self.unread.take().into_iter().collect();
+        unread.sort();
+        unread
+    }
+
     // Returns whether or not a value exists in our identifiers list
     pub fn has(&self, name: String) -> bool {
         if self.locals.contains_key(&name) {
             return true;
         }
-        let mut found = false;
-        while let Some(parent) = self.parent.clone() {
-            found = parent.locals.contains_key(&name);
-            if found {
-                break;
-            }
+        match &self.parent {
+            Some(parent) => parent.has(name),
+            None => false,
         }
-        found
     }
     // Returns a possible value
     pub fn lookup(&self, name: String) -> Option<Expr> {
@@ -1,9 +1,48 @@
-use crate::ast::Expr;
+use crate::ast::{Expr, Pattern};
 
 pub fn is_ident(expr: &Expr) -> bool {
     matches!(expr, Expr::Ident(_))
 }
 
+// Whether an expression could evaluate to something callable
+pub fn is_callable(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Ident(_)
+            | Expr::Function { .. }
+            | Expr::Call { .. }
+            | Expr::Member { .. }
+            | Expr::If { .. }
+            | Expr::Match { .. }
+    )
+}
+
 pub fn is_string(expr: &Expr) -> bool {
     matches!(expr, Expr::String(_))
 }
+
+// Whether a pattern could match the value of an expression
+// Only literals are known well enough to rule anything out, so everything else could match
+pub fn can_match(pattern: &Pattern, value: &Expr) -> bool {
+    match (pattern, value) {
+        (Pattern::Nothing, _) | (Pattern::Ident(_), _) => true,
+        (Pattern::Number(_) | Pattern::Range(..), Expr::Integer(_) | Expr::Number(_)) => true,
+        (Pattern::String(_), Expr::String(_)) => true,
+        (Pattern::Symbol(_), Expr::Symbol(_)) => true,
+        (Pattern::Boolean(_), Expr::Boolean(_)) => true,
+        (Pattern::Array(_), Expr::Array(_)) => true,
+        (Pattern::Hash(_), Expr::Hash(_)) => true,
+        (_, value) => !matches!(
+            value,
+            Expr::Integer(_)
+                | Expr::Number(_)
+                | Expr::String(_)
+                | Expr::Symbol(_)
+                | Expr::Char(_)
+                | Expr::Boolean(_)
+                | Expr::Array(_)
+                | Expr::Hash(_)
+                | Expr::Null
+        ),
+    }
+}
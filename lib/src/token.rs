@@ -29,21 +29,32 @@ pub enum TokenType {
     Eof,
 
     Ident(String),  // foobar
-    Number(f64),    // Integer or float
+    Integer(i64),   // Whole number literal, like 5
+    Number(f64),    // Float literal, like 5.5
     String(String), // "hello world"
     Symbol(String), // Self representing value, like :true
+    Char(char),     // `a`
 
     // Symbols and Operators
     Assign,   // =
     Plus,     // +
     Minus,    // -
     Asterisk, // *
+    Power,    // **
     Modulus,  // %
     Slash,    // /
     Period,   // .
     Arrow,    // ->
     Range,    // .. (like 0..5)
+    Spread,   // ... (like [first, ...rest])
     Match,    // ::
+    Pipe,     // |>
+    // Compound assignment
+    PlusAssign,     // +=
+    MinusAssign,    // -=
+    AsteriskAssign, // *=
+    SlashAssign,    // /=
+    ModulusAssign,  // %=
     // Boolean operators
     Greater,   // >
     Less,      // <
@@ -57,6 +68,13 @@ pub enum TokenType {
     And, // &&
     Or,  // ||
 
+    // Bitwise operators
+    BitAnd,     // &
+    BitOr,      // |
+    BitXor,     // ^
+    ShiftLeft,  // <<
+    ShiftRight, // >>
+
     // Delimiters
     Comma,
     Semicolon,
@@ -79,11 +97,15 @@ pub enum TokenType {
     As,
 
     Return,
+    Break,
+    Continue,
     Function,
     True,
     False,
+    Null,
     If,
     Else,
+    While,
     Then,
     Let,
 }
@@ -92,20 +114,30 @@ impl fmt::Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             TokenType::Ident(value) => write!(f, "{}", value),
+            TokenType::Integer(value) => write!(f, "{}", value),
             TokenType::Number(value) => write!(f, "{}", value),
             TokenType::String(value) => write!(f, "'{}'", value),
             TokenType::Symbol(value) => write!(f, ":{}", value),
+            TokenType::Char(value) => write!(f, "`{}`", value),
 
             TokenType::Assign => write!(f, "="),   // =
             TokenType::Plus => write!(f, "+"),     // +
             TokenType::Minus => write!(f, "-"),    // -
             TokenType::Asterisk => write!(f, "*"), // *
+            TokenType::Power => write!(f, "**"),   // **
             TokenType::Modulus => write!(f, "%"),  // %
             TokenType::Slash => write!(f, "/"),    // /
             TokenType::Period => write!(f, "."),   // .
             TokenType::Arrow => write!(f, "->"),   // ->
             TokenType::Range => write!(f, ".."),   // .. (like 0..5)
+            TokenType::Spread => write!(f, "..."),
             TokenType::Match => write!(f, "::"),
+            TokenType::Pipe => write!(f, "|>"),
+            TokenType::PlusAssign => write!(f, "+="),
+            TokenType::MinusAssign => write!(f, "-="),
+            TokenType::AsteriskAssign => write!(f, "*="),
+            TokenType::SlashAssign => write!(f, "/="),
+            TokenType::ModulusAssign => write!(f, "%="),
             TokenType::Greater => write!(f, ">"),    // >
             TokenType::Less => write!(f, "<"),       // <
             TokenType::GreaterEq => write!(f, ">="), // >=
@@ -117,6 +149,12 @@ impl fmt::Display for TokenType {
             TokenType::And => write!(f, "&&"),
             TokenType::Or => write!(f, "||"),
 
+            TokenType::BitAnd => write!(f, "&"),
+            TokenType::BitOr => write!(f, "|"),
+            TokenType::BitXor => write!(f, "^"),
+            TokenType::ShiftLeft => write!(f, "<<"),
+            TokenType::ShiftRight => write!(f, ">>"),
+
             TokenType::Comma => write!(f, ","),
             TokenType::Semicolon => write!(f, ";"),
             TokenType::Colon => write!(f, ":"), // :
@@ -138,11 +176,15 @@ impl fmt::Display for TokenType {
             TokenType::As => write!(f, "as"),
 
             TokenType::Return => write!(f, "return"),
+            TokenType::Break => write!(f, "break"),
+            TokenType::Continue => write!(f, "continue"),
             TokenType::Function => write!(f, "fn"),
             TokenType::True => write!(f, "true"),
             TokenType::False => write!(f, "false"),
+            TokenType::Null => write!(f, "null"),
             TokenType::If => write!(f, "if"),
             TokenType::Else => write!(f, "else"),
+            TokenType::While => write!(f, "while"),
             TokenType::Then => write!(f, "then"),
 
             TokenType::Eof => write!(f, "EOF"),
@@ -158,11 +200,15 @@ pub fn lookup_keyword(name: &str) -> TokenType {
         "as" => TokenType::As,
         "fn" => TokenType::Function,
         "return" => TokenType::Return,
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
         "true" => TokenType::True,
         "false" => TokenType::False,
+        "null" => TokenType::Null,
         "if" => TokenType::If,
         "then" => TokenType::Then,
         "else" => TokenType::Else,
+        "while" => TokenType::While,
         "let" => TokenType::Let,
         _ => TokenType::Ident(name.to_string()),
     }
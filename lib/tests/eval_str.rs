@@ -0,0 +1,35 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use lib::evaluation::{env::Environment, object::Object};
+
+#[test]
+fn test_eval_str() {
+    assert_eq!(lib::eval_str("1 + 2"), Ok(Object::Number(3.0)));
+    assert_eq!(
+        lib::eval_str("let x = 'a'; x + 'b'"),
+        Ok(Object::String("ab".to_string()))
+    );
+}
+
+#[test]
+fn test_eval_str_errors() {
+    let err = lib::eval_str("let x = ;").unwrap_err();
+    assert!(err.contains("No prefix parser found"), "{}", err);
+
+    let err = lib::eval_str("missing").unwrap_err();
+    assert!(err.contains("Identifier not found"), "{}", err);
+}
+
+#[test]
+fn test_eval_str_with_env() {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    assert_eq!(
+        lib::eval_str_with_env("let x = 5", Rc::clone(&env)),
+        Ok(Object::Void)
+    );
+    assert_eq!(
+        lib::eval_str_with_env("x * 2", env),
+        Ok(Object::Number(10.0))
+    );
+}
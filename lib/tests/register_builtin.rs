@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use lib::evaluation::{env::Environment, object::Object, Evaluator};
+use lib::lexer::Lexer;
+use lib::parser::Parser;
+use lib::{EvalError, EvaluatorConfig};
+
+fn double<'a>(
+    args: Vec<Object<'a>>,
+    _: Rc<RefCell<Evaluator<'a>>>,
+) -> Result<Object<'a>, EvalError> {
+    match args[0].as_number() {
+        Some(n) => Ok(Object::Number(n * 2.0)),
+        None => Err(EvalError::TypeMismatch(format!(
+            "{} isn't a number",
+            args[0]
+        ))),
+    }
+}
+
+fn eval_with_double(source: &str) -> Result<Object<'static>, EvalError> {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut evaluator = Evaluator::new(env, EvaluatorConfig::default());
+    let func: lib::BuiltinFunc = double;
+    evaluator.register_builtin("double", 1, func);
+
+    let program = Parser::new(Lexer::new(source), source.to_string())
+        .parse_program()
+        .unwrap();
+    evaluator.eval_program(program)
+}
+
+#[test]
+fn test_register_builtin() {
+    assert_eq!(eval_with_double("double(21)"), Ok(Object::Number(42.0)));
+    assert_eq!(
+        eval_with_double("map([1, 2], fn x -> double(x))"),
+        Ok(Object::Array(vec![
+            Object::Number(2.0),
+            Object::Number(4.0)
+        ]))
+    );
+}
+
+#[test]
+fn test_registered_builtin_errors() {
+    let err = eval_with_double("double('a')").unwrap_err();
+    assert_eq!(err.to_string(), "'a' isn't a number");
+    let err = eval_with_double("double(1, 2)").unwrap_err();
+    assert_eq!(
+        err,
+        EvalError::WrongArity {
+            expected: "1".to_string(),
+            found: 2
+        }
+    );
+}
@@ -1,52 +1,139 @@
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::path;
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use lib::ast::{Expr, Program};
 use lib::evaluation;
 use lib::lexer::Lexer;
 use lib::parser::error::ParseError;
 use lib::parser::Parser;
+use lib::semantics::{analyze, context::Context};
 use lib::style;
+use lib::EvaluatorConfig;
 use path::Path;
 
-/// Executes a file
+#[cfg(test)]
+#[path = "./file_test.rs"]
+mod file_test;
+
+/// Executes a file, exposing `args` to it as the global `args` array
 /// The file should already be stat-ed to ensure we can access it
-pub fn exec_file(path: &Path) -> io::Result<()> {
+///
+/// Returns whether it ran without any parse, analysis, or runtime errors
+pub fn exec_file(path: &Path, args: Vec<String>) -> io::Result<bool> {
     let file = fs::read_to_string(path)?;
+    exec_source(&file, args, &mut io::stdout())
+}
 
-    let lexer = Lexer::new(&file);
-    let mut parser = Parser::new(lexer, file.to_string());
-
-    let program = match parser.parse_program() {
-        Ok(program) => program,
-        Err(error) => {
-            handle_parser_error(error);
-            return Ok(());
-        }
+/// Executes a whole program, like the contents of a file, printing its result to `out`
+///
+/// Returns whether it ran without any errors, like exec_file
+pub fn exec_source(source: &str, args: Vec<String>, out: &mut impl Write) -> io::Result<bool> {
+    let program = match check_source(source) {
+        Some(program) => program,
+        None => return Ok(false),
     };
 
     let env = evaluation::env::Environment::new();
 
-    let mut evaluator = evaluation::Evaluator::new(Rc::new(RefCell::new(env)));
+    let mut evaluator =
+        evaluation::Evaluator::new(Rc::new(RefCell::new(env)), EvaluatorConfig::default());
+    evaluator.set_args(args);
 
     let result = evaluator.eval_program(program);
     match result {
         Ok(result) => {
-            println!("{}", result);
+            writeln!(out, "{}", result)?;
+            Ok(true)
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            Ok(false)
+        }
+    }
+}
+
+/// Lexes, parses, and analyzes a file without running it, reporting any errors
+///
+/// Returns whether there weren't any errors
+pub fn check_file(path: &Path) -> io::Result<bool> {
+    let file = fs::read_to_string(path)?;
+    Ok(check_source(&file).is_some())
+}
+
+/// Parses a file and prints its syntax tree to `out`, for debugging the parser
+///
+/// Analysis is skipped, so this works for programs that parse but wouldn't run.
+/// Returns whether it parsed
+pub fn print_ast(path: &Path, out: &mut impl Write) -> io::Result<bool> {
+    let file = fs::read_to_string(path)?;
+    let mut parser = Parser::new(Lexer::new(&file), file.clone());
+    match parser.parse_program() {
+        Ok(program) => {
+            writeln!(out, "{:#?}", program)?;
+            Ok(true)
+        }
+        Err(errors) => {
+            handle_parser_errors(errors);
+            Ok(false)
         }
-        Err(error) => eprintln!("{}", error),
     }
+}
+
+// Gives back the program if it parsed and passed analysis, after reporting any errors
+fn check_source(source: &str) -> Option<Program> {
+    let lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer, source.to_string());
 
-    Ok(())
+    let program = match parser.parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            handle_parser_errors(errors);
+            return None;
+        }
+    };
+
+    let mut context = Context::new_global();
+    context.add("args".to_string(), Expr::Symbol("args".to_string()));
+    let analysis = analyze::analyze_stmts(program.clone(), Some(&mut context));
+    handle_analysis_warnings(context.take_warnings());
+    if let Err(errors) = analysis {
+        handle_analysis_errors(errors);
+        return None;
+    }
+    Some(program)
 }
 
-fn handle_parser_error(error: ParseError) {
+fn handle_parser_errors(errors: Vec<ParseError>) {
     eprintln!(
         "{}\nWe had a few problems while parsing your code",
         style::bold("Parsing Errors:")
     );
-    eprintln!("{}", error);
+    for error in errors {
+        eprintln!("{}", error);
+    }
+}
+
+// Warnings are reported, but don't stop the program from running
+fn handle_analysis_warnings(warnings: Vec<String>) {
+    if warnings.is_empty() {
+        return;
+    }
+    eprintln!("{}", style::bold("Analysis Warnings:"));
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+}
+
+fn handle_analysis_errors(errors: Vec<String>) {
+    eprintln!(
+        "{}\nWe found a few problems while analyzing your code",
+        style::bold("Analysis Errors:")
+    );
+    for error in errors {
+        eprintln!("{}", error);
+    }
 }
@@ -0,0 +1,22 @@
+use super::*;
+
+fn run(source: &str, args: &[&str]) -> (bool, String) {
+    let mut out = vec![];
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    let success = exec_source(source, args, &mut out).unwrap();
+    (success, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_exec_source() {
+    assert_eq!(run("let x = 1 + 2; x * 2", &[]), (true, "6\n".to_string()));
+    assert_eq!(run("args[0]", &["first"]), (true, "'first'\n".to_string()));
+}
+
+#[test]
+fn test_exec_source_errors() {
+    // Errors go to stderr instead
+    assert_eq!(run("let x = ", &[]), (false, String::new()));
+    assert_eq!(run("missing + 1", &[]), (false, String::new()));
+    assert_eq!(run("1 / 0", &[]), (false, String::new()));
+}
@@ -0,0 +1,77 @@
+use std::io::{self, Read, Write};
+
+use lib::context::Context;
+use lib::lexer::Lexer;
+use lib::location::Location;
+use lib::parser::error::ParseError;
+use lib::parser::Parser;
+
+#[cfg(test)]
+#[path = "./lsp_test.rs"]
+mod lsp_test;
+
+/// Reads a program from stdin and writes its diagnostics to stdout as a JSON array
+///
+/// This isn't a full language server, just enough for an editor to show parse errors
+pub fn start() -> io::Result<()> {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}", diagnostics(&source))?;
+    stdout.flush()
+}
+
+/// Parses `source`, returning every error as a JSON object with a message and a location
+pub fn diagnostics(source: &str) -> String {
+    let mut parser = Parser::new(Lexer::new(source), source.to_string());
+    let errors = match parser.parse_program() {
+        Ok(_) => vec![],
+        Err(errors) => errors,
+    };
+    let items: Vec<String> = errors
+        .iter()
+        .map(|error| diagnostic(error, source))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn diagnostic(error: &ParseError, source: &str) -> String {
+    let context: Vec<String> = error
+        .get_context()
+        .iter()
+        .map(|context| string(context))
+        .collect();
+    format!(
+        "{{\"severity\":\"error\",\"message\":{},\"context\":[{}],\"start\":{},\"end\":{}}}",
+        string(&error.kind.to_string()),
+        context.join(","),
+        location(error.position.start, source),
+        location(error.position.end, source)
+    )
+}
+
+fn location(offset: usize, source: &str) -> String {
+    let location = Location::from(offset, source);
+    format!(
+        "{{\"offset\":{},\"line\":{},\"column\":{}}}",
+        offset, location.line, location.column
+    )
+}
+
+// Quotes and escapes `text` as a JSON string
+fn string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,24 @@
+use super::*;
+
+#[test]
+fn test_no_diagnostics() {
+    assert_eq!(diagnostics("let x = 1; x + 1"), "[]");
+}
+
+#[test]
+fn test_parse_diagnostics() {
+    let output = diagnostics("let x 5");
+    assert_eq!(
+        output,
+        "[{\"severity\":\"error\",\
+\"message\":\"Expected token Assign, found Integer(5) instead\",\
+\"context\":[\"Parsing assignment\",\"Parsing program\"],\
+\"start\":{\"offset\":6,\"line\":1,\"column\":7},\
+\"end\":{\"offset\":7,\"line\":1,\"column\":8}}]"
+    );
+}
+
+#[test]
+fn test_escapes_strings() {
+    assert_eq!(string("say \"hi\"\n\\"), "\"say \\\"hi\\\"\\n\\\\\"");
+}
@@ -1,25 +1,74 @@
 extern crate lib;
 mod file;
+mod lsp;
+mod panic;
 mod repl;
 
 use std::env;
+use std::io::{self, IsTerminal, Read};
 use std::path::Path;
+use std::process;
 
 fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() > 1 {
+    // Scripts and CI rely on a failing program exiting with a non-zero code
+    if !run(args)? {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+// Returns whether everything succeeded
+fn run(args: Vec<String>) -> io::Result<bool> {
+    if args.get(1).map(String::as_str) == Some("--lsp") {
+        panic::install("the language server".to_string());
+        lsp::start()?;
+        Ok(true)
+    } else if let Some(flag @ ("--check" | "--ast")) = args.get(1).map(String::as_str) {
+        // Neither of these runs anything
+        let path = match args.get(2) {
+            Some(path) => Path::new(path),
+            None => {
+                eprintln!("Usage: bliss {} <file>", flag);
+                return Ok(false);
+            }
+        };
+        if !path.exists() {
+            eprintln!("The file {} doesn't exist.", path.display());
+            return Ok(false);
+        }
+        panic::install(path.display().to_string());
+        if flag == "--ast" {
+            file::print_ast(path, &mut io::stdout())
+        } else {
+            file::check_file(path)
+        }
+    } else if args.get(1).map(String::as_str) == Some("-")
+        || (args.len() == 1 && !io::stdin().is_terminal())
+    {
+        // Piped programs run like files, as in `echo '1 + 2' | bliss -`
+        panic::install("stdin".to_string());
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        file::exec_source(
+            &source,
+            args.iter().skip(2).cloned().collect(),
+            &mut io::stdout(),
+        )
+    } else if args.len() > 1 {
         let path = Path::new(&args[1]);
 
-        let exists = std::path::Path::exists(path);
-        if !exists {
-            panic!("The file {} doesn't exist.", exists)
+        if !path.exists() {
+            eprintln!("The file {} doesn't exist.", path.display());
+            return Ok(false);
         }
 
-        file::exec_file(path)?;
+        panic::install(path.display().to_string());
+        file::exec_file(path, args[2..].to_vec())
     } else {
+        panic::install("the REPL".to_string());
         repl::start();
+        Ok(true)
     }
-
-    Ok(())
 }
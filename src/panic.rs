@@ -0,0 +1,47 @@
+use std::any::Any;
+use std::env;
+use std::panic::{self, Location};
+
+use lib::style;
+
+#[cfg(test)]
+#[path = "./panic_test.rs"]
+mod panic_test;
+
+/// Replaces Rust's default panic output with a short message asking the user to report the bug
+/// `source` describes what was being run, like a file path
+///
+/// Setting `RUST_BACKTRACE` still prints the default output after the message
+pub fn install(source: String) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        eprintln!("{}", report(&source, info.payload(), info.location()));
+        if env::var_os("RUST_BACKTRACE").is_some() {
+            default_hook(info);
+        }
+    }));
+}
+
+/// Builds the message shown when the interpreter panics
+pub fn report(source: &str, payload: &(dyn Any + Send), location: Option<&Location>) -> String {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown error"
+    };
+    let location = match location {
+        Some(location) => format!(" (at {}:{})", location.file(), location.line()),
+        None => String::new(),
+    };
+    format!(
+        "{} while running {}: {}{}
+This is a bug in bliss, not your code. Please report it at https://github.com/ajkachnic/bliss/issues
+Set RUST_BACKTRACE=1 to see a backtrace",
+        style::bold("Internal interpreter error"),
+        source,
+        message,
+        location
+    )
+}
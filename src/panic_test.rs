@@ -0,0 +1,33 @@
+use super::*;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_friendly_message() {
+    let reported = Arc::new(Mutex::new(None));
+    let captured = reported.clone();
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = report("example.bliss", info.payload(), info.location());
+        *captured.lock().unwrap() = Some(report);
+    }));
+    // Force an internal error, like an `unwrap` on a missing value
+    let result = panic::catch_unwind(|| {
+        let values: Vec<i32> = vec![];
+        values.first().copied().expect("value should exist")
+    });
+    panic::set_hook(previous);
+
+    assert!(result.is_err());
+    let report = reported.lock().unwrap().take().unwrap();
+    assert!(report.contains("Internal interpreter error"));
+    assert!(report.contains("while running example.bliss: value should exist"));
+    assert!(report.contains("panic_test.rs:"));
+    assert!(report.contains("Please report it"));
+}
+
+#[test]
+fn test_formatted_payload() {
+    let payload: Box<dyn Any + Send> = Box::new(format!("bad index {}", 3));
+    let report = report("the REPL", payload.as_ref(), None);
+    assert!(report.contains("while running the REPL: bad index 3\n"));
+}
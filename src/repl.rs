@@ -1,25 +1,59 @@
 use evaluation::Evaluator;
+use rustyline::completion::Completer;
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Editor, Helper};
 
 use std::cell::RefCell;
+use std::io::{self, Write};
 use std::rc::Rc;
 
 use lib::evaluation;
+use lib::evaluation::builtins;
+use lib::evaluation::env::Environment;
+use lib::evaluation::object::Object;
 use lib::lexer;
 use lib::parser::Parser;
+use lib::semantics::{analyze, context::Context};
 use lib::style;
+use lib::token::TokenType;
+use lib::EvaluatorConfig;
+
+#[cfg(test)]
+#[path = "./repl_test.rs"]
+mod repl_test;
 
 pub fn start() {
-    let mut rl = Editor::<()>::new();
-    let env = evaluation::env::Environment::new();
-    let mut evaluator = evaluation::Evaluator::new(Rc::new(RefCell::new(env)));
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut rl = Editor::new();
+    rl.set_helper(Some(ReplHelper {
+        env: Rc::clone(&env),
+    }));
+    let mut evaluator = evaluation::Evaluator::new(Rc::clone(&env), EvaluatorConfig::default());
+    let mut context = Context::new_global();
+    let mut stdout = io::stdout();
     loop {
         let readline = rl.readline(">> ");
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                eval(line.as_str() /* &mut context*/, &mut evaluator)
+                let result = match parse_command(&line) {
+                    Some(Command::Quit) => break,
+                    Some(Command::Env) => print_env(&env.borrow(), &mut stdout),
+                    None => eval(
+                        line.as_str(),
+                        &mut context,
+                        &env,
+                        &mut evaluator,
+                        &mut stdout,
+                    ),
+                };
+                if let Err(err) = result {
+                    println!("Error: {:?}", err);
+                    break;
+                }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
@@ -37,26 +71,194 @@ pub fn start() {
     }
 }
 
-fn eval(line: &str, /*context: &mut Context,*/ eval: &mut Evaluator) {
+// Hooks into rustyline to complete names as you type
+struct ReplHelper<'a> {
+    env: Rc<RefCell<Environment<'a>>>,
+}
+
+impl<'a> Completer for ReplHelper<'a> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(completions(&self.env.borrow(), line, pos))
+    }
+}
+
+impl<'a> Hinter for ReplHelper<'a> {
+    type Hint = String;
+}
+impl<'a> Highlighter for ReplHelper<'a> {}
+// Keeps reading lines until the brackets are balanced, so blocks can span several lines
+impl<'a> Validator for ReplHelper<'a> {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(match bracket_balance(ctx.input()) {
+            Balance::Balanced => ValidationResult::Valid(None),
+            Balance::Pending => ValidationResult::Incomplete,
+            Balance::Unmatched(tok) => {
+                ValidationResult::Invalid(Some(format!("  <- unmatched {}", tok)))
+            }
+        })
+    }
+}
+impl<'a> Helper for ReplHelper<'a> {}
+
+// Finds the names that complete the word before `pos`, along with where that word starts
+fn completions(env: &Environment, line: &str, pos: usize) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before
+        .rfind(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+        .map_or(0, |index| index + 1);
+    let prefix = &before[start..];
+    // Properties (`a.b`) and symbols (`:b`) aren't bound names
+    if prefix.is_empty() || before[..start].ends_with(['.', ':']) {
+        return (pos, vec![]);
+    }
+
+    let mut names: Vec<String> = env
+        .get_store()
+        .keys()
+        .cloned()
+        .chain(builtins::get_builtins().into_keys())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names.dedup();
+    (start, names)
+}
+
+#[derive(Debug, PartialEq)]
+enum Balance {
+    // Ready to evaluate
+    Balanced,
+    // Waiting on a closing bracket or quote
+    Pending,
+    // A closing bracket that doesn't match what's open
+    Unmatched(TokenType),
+}
+
+fn bracket_balance(input: &str) -> Balance {
+    let mut open = vec![];
+    for token in lexer::Lexer::new(input).tokenize() {
+        match token.tok {
+            TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => {
+                open.push(token.tok)
+            }
+            TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => {
+                match (open.pop(), &token.tok) {
+                    (Some(TokenType::LeftParen), TokenType::RightParen)
+                    | (Some(TokenType::LeftBrace), TokenType::RightBrace)
+                    | (Some(TokenType::LeftBracket), TokenType::RightBracket) => {}
+                    _ => return Balance::Unmatched(token.tok),
+                }
+            }
+            // An unterminated string runs to the end of the input
+            TokenType::Illegal
+                if matches!(input.chars().nth(token.position.start), Some('\'' | '"')) =>
+            {
+                return Balance::Pending
+            }
+            _ => {}
+        }
+    }
+    if open.is_empty() {
+        Balance::Balanced
+    } else {
+        Balance::Pending
+    }
+}
+
+// Meta-commands, which control the REPL instead of being evaluated
+#[derive(Debug, PartialEq)]
+enum Command {
+    // Lists everything that's been defined
+    Env,
+    Quit,
+}
+
+// Only exact matches count, since a line like `:ok` is a symbol
+fn parse_command(line: &str) -> Option<Command> {
+    match line.trim() {
+        ":env" => Some(Command::Env),
+        ":quit" | ":q" => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+// Prints the names bound at the top level, leaving out builtins
+fn print_env(env: &Environment, out: &mut impl Write) -> io::Result<()> {
+    let mut names: Vec<(&String, &Object)> = env
+        .get_store()
+        .iter()
+        .filter(|(_, value)| !matches!(value, Object::Builtin(..)))
+        .collect();
+    names.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in names {
+        writeln!(out, "{} = {}", name, value)?;
+    }
+    Ok(())
+}
+
+// Output goes to `out` rather than straight to stdout, so it can be tested
+// `env` is the one `eval` runs in
+fn eval(
+    line: &str,
+    context: &mut Context,
+    env: &RefCell<Environment>,
+    eval: &mut Evaluator,
+    out: &mut impl Write,
+) -> io::Result<()> {
     let l = lexer::Lexer::new(line);
     let mut p = Parser::new(l, line.to_string());
 
     let program = p.parse_program();
     if let Ok(program) = program {
-        // let analysis = semantics::analyze::analyze_stmts(program.clone(), Some(context));
-        // match Ok(true) {
-        // Ok(_) => {
-        let evaled = eval.eval_program(program);
-        if let Ok(evaled) = evaled {
-            println!("{}", evaled);
-        } else if let Err(error) = evaled {
-            println!("An error occurred while evaluating your code:\n{}", error);
+        // A line's bindings only carry over to the next one once it runs without errors,
+        // so analysis and the environment agree on what's been declared
+        let mut scratch = context.clone();
+        let analysis = analyze::analyze_stmts(program.clone(), Some(&mut scratch));
+        // Redefining names is normal while experimenting, so warnings aren't worth showing here
+        scratch.take_warnings();
+        if let Err(errors) = analysis {
+            writeln!(
+                out,
+                "{}\nWe found a few problems while analyzing your code",
+                style::bold("Analysis Errors:")
+            )?;
+            for error in errors {
+                writeln!(out, "{}", error)?;
+            }
+            return Ok(());
+        }
+        let snapshot = env.borrow().clone();
+        let result = eval.eval_program(program);
+        match result {
+            Ok(_) => *context = scratch,
+            Err(_) => *env.borrow_mut() = snapshot,
+        }
+        match result {
+            // Statements like `let` don't produce anything worth printing
+            Ok(Object::Void) => {}
+            Ok(evaled) => writeln!(out, "{}", evaled)?,
+            Err(error) => writeln!(
+                out,
+                "An error occurred while evaluating your code:\n{}",
+                error
+            )?,
+        }
+    } else if let Err(errors) = program {
+        writeln!(
+            out,
+            "{}\nWe had a few problems while parsing your code",
+            style::bold("Parse Errors:")
+        )?;
+        for error in errors {
+            writeln!(out, "{}", error)?;
         }
-    } else if let Err(error) = program {
-        println!(
-            "{}\nWe had a problem while parsing your code",
-            style::bold("Parse Error:")
-        );
-        println!("{}", error);
     }
+    Ok(())
 }
@@ -0,0 +1,136 @@
+use super::*;
+
+// Runs each line through the REPL in order, returning what the last one printed
+fn run(lines: &[&str]) -> String {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut evaluator = evaluation::Evaluator::new(Rc::clone(&env), EvaluatorConfig::default());
+    let mut context = Context::new_global();
+    let mut out = vec![];
+    for line in lines {
+        out.clear();
+        eval(line, &mut context, &env, &mut evaluator, &mut out).unwrap();
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_void_is_hidden() {
+    assert_eq!(run(&["let x = 5"]), "");
+    assert_eq!(run(&["let x = 5", "x"]), "5\n");
+}
+
+#[test]
+fn test_null_is_shown() {
+    assert_eq!(run(&["null"]), "null\n");
+    assert_eq!(run(&["{}.missing"]), "null\n");
+}
+
+#[test]
+fn test_commands() {
+    assert_eq!(parse_command(":env"), Some(Command::Env));
+    assert_eq!(parse_command(" :quit "), Some(Command::Quit));
+    assert_eq!(parse_command(":q"), Some(Command::Quit));
+    // Anything else is evaluated, so symbols still work
+    assert_eq!(parse_command(":ok"), None);
+    assert_eq!(parse_command("x"), None);
+}
+
+#[test]
+fn test_print_env() {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut evaluator = evaluation::Evaluator::new(Rc::clone(&env), EvaluatorConfig::default());
+    let mut context = Context::new_global();
+    let mut out = vec![];
+    eval(
+        "let b = [1, 2]; let a = 'hi'",
+        &mut context,
+        &env,
+        &mut evaluator,
+        &mut out,
+    )
+    .unwrap();
+
+    print_env(&env.borrow(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a = 'hi'\nb = [1, 2]\n");
+}
+
+#[test]
+fn test_completions() {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut evaluator = evaluation::Evaluator::new(Rc::clone(&env), EvaluatorConfig::default());
+    let mut context = Context::new_global();
+    eval(
+        "let keep = 1; let kept = 2",
+        &mut context,
+        &env,
+        &mut evaluator,
+        &mut vec![],
+    )
+    .unwrap();
+    let env = env.borrow();
+
+    let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    // Both bindings and builtins are offered
+    assert_eq!(
+        completions(&env, "ke", 2),
+        (0, names(&["keep", "kept", "keys"]))
+    );
+    assert_eq!(completions(&env, "log(kep", 7), (4, names(&["kept"])));
+    // Only the word before the cursor counts
+    assert_eq!(completions(&env, "kep + 1", 3), (0, names(&["kept"])));
+    assert_eq!(completions(&env, "x.ke", 4), (4, vec![]));
+    assert_eq!(completions(&env, "", 0), (0, vec![]));
+}
+
+#[test]
+fn test_bracket_balance() {
+    assert_eq!(bracket_balance("let x = [1, (2 + 3)]"), Balance::Balanced);
+    assert_eq!(bracket_balance("log('(')"), Balance::Balanced);
+    assert_eq!(bracket_balance(""), Balance::Balanced);
+
+    assert_eq!(bracket_balance("let f = fn x -> {"), Balance::Pending);
+    assert_eq!(bracket_balance("if x {\n  [1, 2"), Balance::Pending);
+    assert_eq!(bracket_balance("let s = 'unfinished"), Balance::Pending);
+
+    assert_eq!(
+        bracket_balance("[1, 2)"),
+        Balance::Unmatched(TokenType::RightParen)
+    );
+    assert_eq!(
+        bracket_balance("x }"),
+        Balance::Unmatched(TokenType::RightBrace)
+    );
+}
+
+#[test]
+fn test_failed_lines_declare_nothing() {
+    let output = run(&["let x = missing()", "x"]);
+    assert!(
+        output.starts_with(&style::bold("Analysis Errors:")),
+        "{}",
+        output
+    );
+    let output = run(&["let x = 1 / 0", "x"]);
+    assert!(
+        output.starts_with(&style::bold("Analysis Errors:")),
+        "{}",
+        output
+    );
+    assert_eq!(run(&["let x = 1 / 0", "let x = 2", "x"]), "2\n");
+
+    // Bindings from earlier in a failed line are undone too, so `:env` agrees with analysis
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut evaluator = evaluation::Evaluator::new(Rc::clone(&env), EvaluatorConfig::default());
+    let mut context = Context::new_global();
+    let line = "let a = 1; let b = 1 / 0";
+    eval(line, &mut context, &env, &mut evaluator, &mut vec![]).unwrap();
+    let mut out = vec![];
+    print_env(&env.borrow(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "");
+    let output = run(&[line, "a"]);
+    assert!(
+        output.starts_with(&style::bold("Analysis Errors:")),
+        "{}",
+        output
+    );
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs the bliss binary on a file containing `source`
+fn run(name: &str, source: &str) -> Output {
+    run_with(&[], name, source)
+}
+
+// Like run, but passes `flags` before the file
+fn run_with(flags: &[&str], name: &str, source: &str) -> Output {
+    // The process id keeps concurrent runs of the suite from sharing files
+    let path =
+        std::env::temp_dir().join(format!("bliss_cli_{}_{}.bliss", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_bliss"))
+        .args(flags)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+// Runs the bliss binary with `args`, piping `input` to it
+fn run_stdin(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bliss"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_stdin() {
+    let output = run_stdin(&["-"], "1 + 2");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "3\n");
+    let output = run_stdin(&["-", "a", "b"], "args");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "['a', 'b']\n");
+    // Without a terminal, stdin is read even without the dash
+    let output = run_stdin(&[], "2 * 3");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6\n");
+}
+
+#[test]
+fn test_inspect_prints() {
+    let output = run("inspect", "inspect(5) + 1");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "5\n6\n");
+}
+
+#[test]
+fn test_exit_codes() {
+    assert_eq!(run("success", "let x = 1; x").status.code(), Some(0));
+    let parse_error = run("parse_error", "let x = ;");
+    assert_eq!(parse_error.status.code(), Some(1));
+    // Errors go to stderr, so they never mix with the program's own output
+    assert!(parse_error.stdout.is_empty());
+    assert_eq!(run("runtime_error", "[1][5]").status.code(), Some(1));
+
+    let missing = Command::new(env!("CARGO_BIN_EXE_bliss"))
+        .arg("definitely_missing.bliss")
+        .output()
+        .unwrap();
+    assert_eq!(missing.status.code(), Some(1));
+}
+
+#[test]
+fn test_ast() {
+    // The tree is printed instead of running the program, even if it wouldn't analyze
+    let output = run_with(&["--ast"], "ast", "log(x ** 2)");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("node: Call {"), "{}", stdout);
+    assert!(stdout.contains("\"**\""), "{}", stdout);
+    assert!(stdout.contains("Integer(\n"), "{}", stdout);
+
+    let output = run_with(&["--ast"], "ast_error", "let x = ;");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_mutual_recursion() {
+    let output = run(
+        "mutual_recursion",
+        "let f = fn n -> if n == 0 { :done } else { g(n - 1) }
+let g = fn n -> f(n)
+f(3)",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), ":done\n");
+}
+
+#[test]
+fn test_check() {
+    // Nothing runs, so nothing gets printed
+    let output = run_with(&["--check"], "check_ok", "log('hi'); 1 + 2");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+
+    let output = run_with(&["--check"], "check_undefined", "let x = 1; x + y");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("used before declaration"), "{}", stderr);
+}